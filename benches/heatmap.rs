@@ -0,0 +1,89 @@
+use battleships::heatmap::gen_heat_field;
+use battleships::types::{rules::Rules, Coordinate, ShotStatus};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use field::Field;
+use std::hint::black_box;
+
+/*
+* Baselines for heat generation, the one part of the program whose cost grows with the board.
+* Every scenario runs at the default 9x7 board, a roomier 12x12 one and a large 20x20 one (width x height),
+* so the incremental recompute and rayon features have something to be measured against.
+*/
+const SIZES: [(usize, usize); 3] = [(9, 7), (12, 12), (20, 20)];
+const FLEET: [usize; 5] = [2, 3, 3, 4, 5];
+
+//the opening move. Nothing is known, so every placement of every ship counts and only the base layer has work to do.
+fn empty_board(width: usize, height: usize) -> (Field<ShotStatus>, Vec<usize>) {
+    (Field::new_default(width, height), FLEET.to_vec())
+}
+
+//a few turns into the hunt: misses spread every seventh cell and one fresh hit near the center, nothing sunk yet.
+//The misses break up the placements and the hit brings the hit layer in.
+fn mid_game_board(width: usize, height: usize) -> (Field<ShotStatus>, Vec<usize>) {
+    let mut shots = Field::new_default(width, height);
+    for row in 0..height {
+        for column in 0..width {
+            if (row * width + column) % 7 == 3 {
+                set(&mut shots, row, column, ShotStatus::Miss);
+            }
+        }
+    }
+    set(&mut shots, height / 2, width / 2, ShotStatus::Hit);
+    (shots, FLEET.to_vec())
+}
+
+//close to the end: half the board missed in a checkerboard, the 5 and the 4 sunk along the edges,
+//and a run of two hits on one of the three ships still afloat. Few placements are left, most of them through the hits.
+fn late_game_board(width: usize, height: usize) -> (Field<ShotStatus>, Vec<usize>) {
+    let mut shots = Field::new_default(width, height);
+    for row in 0..height {
+        for column in 0..width {
+            if (row + column) % 2 == 0 {
+                set(&mut shots, row, column, ShotStatus::Miss);
+            }
+        }
+    }
+    for column in 0..5 {
+        set(&mut shots, 0, column, ShotStatus::Sunk);
+    }
+    for row in 2..6 {
+        set(&mut shots, row, width - 1, ShotStatus::Sunk);
+    }
+    set(&mut shots, height / 2, width / 2, ShotStatus::Hit);
+    set(&mut shots, height / 2, width / 2 + 1, ShotStatus::Hit);
+    (shots, vec![2, 3, 3])
+}
+
+fn set(shots: &mut Field<ShotStatus>, row: usize, column: usize, status: ShotStatus) {
+    shots
+        .set_value(Coordinate { row, column }, &status)
+        .unwrap();
+}
+
+fn bench_gen_heat_field(c: &mut Criterion) {
+    type Scenario = fn(usize, usize) -> (Field<ShotStatus>, Vec<usize>);
+    let scenarios: [(&str, Scenario); 3] = [
+        ("empty", empty_board),
+        ("mid-game", mid_game_board),
+        ("late-game", late_game_board),
+    ];
+    let rules = Rules::classic();
+
+    for (name, scenario) in scenarios {
+        let mut group = c.benchmark_group(format!("gen_heat_field/{name}"));
+        for (width, height) in SIZES {
+            let (shots, ship_lengths) = scenario(width, height);
+            group.bench_with_input(
+                BenchmarkId::from_parameter(format!("{width}x{height}")),
+                &(shots, ship_lengths),
+                |b, (shots, ship_lengths)| {
+                    b.iter(|| gen_heat_field(black_box(shots), black_box(ship_lengths), &rules))
+                },
+            );
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_gen_heat_field);
+criterion_main!(benches);
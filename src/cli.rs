@@ -1,26 +1,109 @@
-use crate::state;
+use crate::heatmap::{FromCsv, ToCsv};
+use crate::options::Options;
+use crate::state::{self, ActionOutcome};
 use crate::types::action::{
     Action,
     Argument::{Known, Unknown},
+    Line,
 };
-use crate::types::{Coordinate, Printable};
+use crate::types::board::parse_board;
+use crate::types::field_ext::FieldExt;
+use crate::types::query::Query;
+use crate::types::rules::{CombineMode, Rules};
+use crate::types::{Coordinate, IndexStyle, Printable, ShotStatus};
 use anyhow::{Ok, Result};
+use field::{helpers::Axis, Field};
+use serde_json::json;
+use std::cell::Cell;
 use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
 use strum::IntoEnumIterator;
 
-pub fn main_loop(state: &mut state::State) {
-    display_help();
+const SHOT_COMMAND: &str = "shot";
+const SETBOARD_COMMAND: &str = "setboard";
+const SETBOARD_HELP: &str = "'setboard' Replaces the whole board with one read from the following lines, up to a blank line.\n\t'.' untested, 'o' miss, 'X' hit, '#' sunk, one row per line. Clears the history.";
+const AUTOPLAY_COMMAND: &str = "autoplay";
+const AUTOPLAY_HELP: &str = "'autoplay <path>' Plays the rest of the game against the layout in the file, always taking the recommended move.\n\tShips are marked 'X' or '#', everything else is water.";
+const REPEAT_HELP: &str = "An empty line repeats the last action exactly as it was typed, so a repeated 'fire' takes the next recommended move.";
+
+//Ends once the input runs out. Only failing to read the input at all is an error.
+pub fn main_loop(state: &mut state::State, options: &Options) -> Result<()> {
+    if options.json {
+        return json_loop(state);
+    }
+    if options.protocol {
+        return protocol_loop(state);
+    }
+
+    display_help(state.index_style());
 
     state.debug_print_state();
     display_recommended_moves(state);
+    //the last action as typed, so inferred arguments get inferred afresh when it is repeated
+    let mut last_action_input: Option<String> = None;
     loop {
         println!("Please enter a command.");
         std::io::stdout().flush().unwrap();
 
-        match play_round(state) {
-            std::result::Result::Ok(success_report) => {
+        let Some(input) = read_input_line()? else {
+            return Ok(());
+        };
+        let was_blank = input.trim().is_empty();
+        let Some(input) = repeated_input(input, last_action_input.as_deref()) else {
+            continue;
+        };
+        if was_blank {
+            println!("Repeating '{input}'.");
+        }
+
+        if let Some(query) = find_query(&input) {
+            match run_query(query, &input, state) {
+                std::result::Result::Ok(report) => println!("{report}"),
+                Err(err) => println!("{err}"),
+            }
+            continue;
+        }
+
+        if let [command, path] = input.split_whitespace().collect::<Vec<_>>()[..] {
+            if command.to_lowercase() == AUTOPLAY_COMMAND {
+                match autoplay(state, Path::new(path)) {
+                    std::result::Result::Ok(report) => {
+                        println!("{report}");
+                        state.debug_print_state();
+                    }
+                    Err(err) => println!("{err}"),
+                }
+                continue;
+            }
+        }
+
+        if input.trim().to_lowercase() == SETBOARD_COMMAND {
+            match set_board(state) {
+                std::result::Result::Ok(()) => {
+                    state.debug_print_state();
+                    display_recommended_moves(state);
+                }
+                Err(err) => println!("{err}"),
+            }
+            continue;
+        }
+
+        let before = Snapshot::of(state);
+        match play_round(&input, state) {
+            std::result::Result::Ok((_, success_report)) => {
+                last_action_input = Some(input.trim().to_owned());
                 println!("{success_report}");
+                if options.verbose {
+                    println!("{}", before.changes(state));
+                }
                 state.debug_print_state();
+                if state.is_complete() {
+                    println!("{}", victory_banner(state));
+                    if !offer_undo(state)? {
+                        return Ok(());
+                    }
+                }
                 display_recommended_moves(state);
             }
             Err(err) => println!("{err}"),
@@ -28,70 +111,483 @@ pub fn main_loop(state: &mut state::State) {
     }
 }
 
-fn play_round(state: &mut state::State) -> Result<String> {
+//an empty line stands for the last action, as typed. None if there is nothing to repeat yet.
+fn repeated_input(input: String, last_action_input: Option<&str>) -> Option<String> {
+    if !input.trim().is_empty() {
+        return Some(input);
+    }
+    last_action_input.map(str::to_owned)
+}
+
+//the parts of the state the verbose report compares before and after an action
+struct Snapshot {
+    untested_count: usize,
+    top_move: Option<Coordinate>,
+}
+
+impl Snapshot {
+    fn of(state: &state::State) -> Snapshot {
+        Snapshot {
+            untested_count: state.get_shots().count_where(ShotStatus::is_untested),
+            top_move: state.recommend(),
+        }
+    }
+
+    fn changes(&self, state: &state::State) -> String {
+        let style = state.index_style();
+        let after = Snapshot::of(state);
+
+        let untested_report = format!(
+            "Untested cells: {} -> {}",
+            self.untested_count, after.untested_count
+        );
+        let top_move_report = match (self.top_move, after.top_move) {
+            (Some(before), Some(after)) if before == after => {
+                format!("Recommended move unchanged: {}", after.printable(style))
+            }
+            (Some(before), Some(after)) => format!(
+                "Recommended move changed: {} -> {}",
+                before.printable(style),
+                after.printable(style)
+            ),
+            (_, Some(after)) => format!("Recommended move is now {}", after.printable(style)),
+            (_, None) => "There is no recommended move anymore.".to_owned(),
+        };
+
+        format!("{untested_report}\n{top_move_report}")
+    }
+}
+
+fn set_board(state: &mut state::State) -> Result<()> {
+    println!("Enter the board, one row per line, followed by a blank line.");
+    std::io::stdout().flush().unwrap();
+
+    let mut text = String::new();
+    loop {
+        let mut line = String::new();
+        if std::io::stdin().read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        text.push_str(&line);
+    }
+
+    state.set_shots(parse_board(&text)?)
+}
+
+fn autoplay(state: &mut state::State, path: &Path) -> Result<String> {
+    let shots_fired = state.autoplay(&read_board(path)?)?;
+    Ok(format!("Sunk the whole fleet in {shots_fired} shots."))
+}
+
+//plays against the board in the file, so fires resolve to hits or misses by themselves
+pub fn load_truth(state: &mut state::State, path: &Path) -> Result<()> {
+    state.set_truth(read_board(path)?)
+}
+
+//weights the heat by the prior in the file, one board row of numbers per line
+pub fn load_prior(state: &mut state::State, path: &Path) -> Result<()> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("Unable to read prior '{}': {err}", path.display()))?;
+    state.set_prior(Field::from_csv(&text)?)
+}
+
+fn read_board(path: &Path) -> Result<Field<ShotStatus>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("Unable to read board '{}': {err}", path.display()))?;
+    parse_board(&text)
+}
+
+fn victory_banner(state: &state::State) -> String {
+    let stats = state.stats();
+    format!(
+        "###############################\nAll ships sunk, you win!\nSunk {} ships in {} shots, with a hit rate of {:.1}%.\n###############################",
+        stats.ships_sunk,
+        stats.shots_fired,
+        stats.hit_rate() * 100.
+    )
+}
+
+//returns whether the game goes on
+fn offer_undo(state: &mut state::State) -> Result<bool> {
+    println!("Enter 'undo' to take back the last action, anything else exits.");
+    std::io::stdout().flush().unwrap();
+
+    let Some(input) = read_input_line()? else {
+        return Ok(false);
+    };
+    if input.trim().to_lowercase() != Action::Undo(Unknown).name() {
+        return Ok(false);
+    }
+
+    match play_round(&input, state) {
+        std::result::Result::Ok((_, success_report)) => {
+            println!("{success_report}");
+            state.debug_print_state();
+            Ok(true)
+        }
+        Err(err) => {
+            println!("{err}");
+            Ok(false)
+        }
+    }
+}
+
+//None once the input is closed
+fn read_input_line() -> Result<Option<String>> {
+    read_line_from(&mut std::io::stdin().lock())
+}
+
+fn read_line_from(reader: &mut impl std::io::BufRead) -> Result<Option<String>> {
     let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
+    let bytes_read = reader
+        .read_line(&mut input)
+        .map_err(|err| anyhow::anyhow!("Unable to read input: {err}"))?;
+    Ok((bytes_read != 0).then_some(input))
+}
+
+//one JSON object per command and nothing else, for front-ends to parse
+fn json_loop(state: &mut state::State) -> Result<()> {
+    //like a script, the JSON reader can't answer the prompt, so an ambiguous sink is reported as a failure
+    let sink_was_ambiguous = Rc::new(Cell::new(false));
+    let resolver_flag = Rc::clone(&sink_was_ambiguous);
+    state.set_location_resolver(move |_| {
+        resolver_flag.set(true);
+        usize::MAX
+    });
+
+    loop {
+        let Some(input) = read_input_line()? else {
+            state.reset_location_resolver();
+            return Ok(());
+        };
+        if input.trim().is_empty() {
+            continue;
+        }
+
+        let mut result = match find_query(&input) {
+            Some(query) => run_query(query, &input, state).map(|report| (None, report)),
+            None => play_round(&input, state).map(|(action, report)| (Some(action), report)),
+        };
+        if sink_was_ambiguous.replace(false) {
+            result = Err(anyhow::anyhow!(
+                "The sunk ship could be in multiple places, which can't be resolved in JSON mode."
+            ));
+        }
+        println!("{}", json_report(result, state));
+        std::io::stdout().flush().unwrap();
+    }
+}
+
+/*
+* A line protocol for tournament harnesses, over stdin and stdout. No board, no help text, exactly one reply per line.
+*
+*   The solver opens with its first shot:   FIRE <column> <row>
+*   The harness reports how it went:        RESULT <column> <row> <miss|hit|sunk:<length>>
+*   The solver replies with its next shot:  FIRE <column> <row>
+*   or, once the whole fleet is sunk:       DONE
+*
+* A line that can't be applied gets 'ERROR <message>' and changes nothing.
+* Once the turn limit ('--max-turns') is hit with ships left afloat, the solver replies with an ERROR and stops.
+* Coordinates follow the index style like everywhere else, and blank lines are skipped.
+*/
+fn protocol_loop(state: &mut state::State) -> Result<()> {
+    println!("{}", protocol_reply(state));
+    std::io::stdout().flush().unwrap();
+
+    let mut turns = 0;
+    loop {
+        let Some(input) = read_input_line()? else {
+            return Ok(());
+        };
+        if input.trim().is_empty() {
+            continue;
+        }
+
+        let reply = match protocol_result(&input, state) {
+            std::result::Result::Ok(()) => {
+                turns += 1;
+                if turns >= state.turn_limit() && !state.is_complete() {
+                    println!("ERROR {}", state.turn_limit_error());
+                    return Ok(());
+                }
+                protocol_reply(state)
+            }
+            Err(err) => format!("ERROR {err}"),
+        };
+        println!("{reply}");
+        std::io::stdout().flush().unwrap();
+    }
+}
+
+fn protocol_reply(state: &state::State) -> String {
+    let style = state.index_style();
+    if state.is_complete() {
+        return "DONE".to_owned();
+    }
+    match state.recommend() {
+        Some(coord) => {
+            let (column, row) = coord.to_user(style);
+            format!("FIRE {column} {row}")
+        }
+        None => "ERROR There are no untested coordinates left.".to_owned(),
+    }
+}
+
+fn protocol_result(input: &str, state: &mut state::State) -> Result<()> {
+    let syntax_error =
+        || anyhow::anyhow!("Expected 'RESULT <column> <row> <miss|hit|sunk:<length>>'.");
+    let ["RESULT", column, row, outcome] = input.split_whitespace().collect::<Vec<_>>()[..] else {
+        return Err(syntax_error());
+    };
+    let coord = parse_coordinate(
+        &[column, row],
+        state.get_shots().width(),
+        state.get_shots().height(),
+        state.index_style(),
+    )?;
+
+    match (outcome, outcome.split_once(':')) {
+        ("miss", _) => {
+            state.take_action(Action::Fire(Known(coord)))?;
+        }
+        ("hit", _) => {
+            state.take_action(Action::Hit(Known(coord)))?;
+        }
+        (_, Some(("sunk", ship_length))) => {
+            let ship_length = parse_number(ship_length)?;
+            state.take_action(Action::Hit(Known(coord)))?;
+            //auto-sink may already have taken care of it
+            if state.status_at(coord) == Some(ShotStatus::Hit) {
+                let previous = state.replace_location_resolver(move |locations| {
+                    locations
+                        .iter()
+                        .position(|location| location.contains(&coord))
+                        .unwrap_or(usize::MAX)
+                });
+                let sunk = state.take_action(Action::Sink(Known(ship_length)));
+                state.restore_location_resolver(previous);
+                if let Err(err) = sunk {
+                    //the hit on its own isn't what the harness reported, so it goes too
+                    state.undo(1)?;
+                    return Err(err);
+                }
+            }
+        }
+        _ => return Err(syntax_error()),
+    }
+
+    Ok(())
+}
+
+fn json_report(result: Result<(Option<Action>, String)>, state: &state::State) -> String {
+    let style = state.index_style();
+    let (action, message) = match result {
+        std::result::Result::Ok(report) => report,
+        Err(err) => return json!({ "ok": false, "error": err.to_string() }).to_string(),
+    };
+
+    let mut report = state.to_json();
+    report["ok"] = json!(true);
+    report["message"] = json!(message);
+
+    if let Some(action) = action {
+        report["action"] = json!(action.name());
+        match action {
+            Action::Fire(Known(coord))
+            | Action::Unfire(Known(coord))
+            | Action::Hit(Known(coord))
+            | Action::Unhit(Known(coord)) => {
+                report["coord"] = json!(coord.to_user(style));
+            }
+            Action::Sink(Known(ship_length)) | Action::Unsink(Known(ship_length)) => {
+                report["length"] = json!(ship_length);
+            }
+            _ => {}
+        }
+    }
+
+    report.to_string()
+}
+
+pub fn run_script(state: &mut state::State, path: &Path, options: &Options) -> Result<()> {
+    let script = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("Unable to read script '{}': {err}", path.display()))?;
+
+    //nobody is around to answer the prompt, so an ambiguous sink has to stop the script
+    let sink_was_ambiguous = Rc::new(Cell::new(false));
+    let resolver_flag = Rc::clone(&sink_was_ambiguous);
+    state.set_location_resolver(move |_| {
+        resolver_flag.set(true);
+        usize::MAX
+    });
+
+    //like at the prompt, an empty line repeats the last action
+    let mut last_action_input: Option<String> = None;
+    for (line_idx, line) in script.lines().enumerate() {
+        let Some(line) = repeated_input(line.to_owned(), last_action_input.as_deref()) else {
+            continue;
+        };
+        //a repeat is echoed as the command it stands for
+        if !options.json {
+            println!("> {}", line.trim());
+        }
+
+        let result = match find_query(&line) {
+            Some(query) => run_query(query, &line, state).map(|report| (None, report)),
+            None => play_round(&line, state).map(|(action, report)| (Some(action), report)),
+        };
+        if let std::result::Result::Ok((Some(_), _)) = &result {
+            last_action_input = Some(line.trim().to_owned());
+        }
+        if options.json {
+            println!("{}", json_report(result, state));
+        } else {
+            match result {
+                std::result::Result::Ok((_, report)) => println!("{report}"),
+                Err(err) => println!("{err}"),
+            }
+        }
+
+        if sink_was_ambiguous.get() {
+            return Err(anyhow::anyhow!(
+                "Line {}: the sunk ship could be in multiple places, which a script can't resolve.",
+                line_idx + 1
+            ));
+        }
+    }
+
+    state.reset_location_resolver();
+    if !options.json {
+        state.debug_print_state();
+    }
+    Ok(())
+}
+
+fn play_round(input: &str, state: &mut state::State) -> Result<(Action, String)> {
+    let style = state.index_style();
+    //against a known board, the report should name what the shot turned out to be
+    let action = state.resolve_against_truth(process_input(input, state)?);
+
+    if let Action::Undo(count) = action {
+        let count = count.known_or(1);
+        let undone = state.undo(count)?;
 
-    let action = process_input(&input, state)?;
+        let mut success_report = undone
+            .iter()
+            .map(|undone_action| {
+                format!(
+                    "Successfully undid '{}'.\n{}",
+                    undone_action.name(),
+                    undone_action.tx_undone(style)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        if undone.len() < count {
+            success_report += &format!("\nStopped after undoing {} actions.", undone.len());
+        }
+        return Ok((action, success_report));
+    }
+
+    let mut success_report = action.tx_success(style);
+    let outcome = state.take_action(action)?;
+
+    //with auto-sinking on, a hit may have been followed up by a sink
+    if let Action::Hit(_) = action {
+        if let std::result::Result::Ok(sink @ Action::Sink(_)) = state.get_last_action() {
+            success_report += "\n";
+            success_report += &sink.tx_success(style);
+        }
+    }
 
-    let mut success_report;
-    if std::mem::discriminant(&action) == std::mem::discriminant(&Action::Undo) {
-        let last_action = state.get_last_action()?;
-        success_report = format!("Successfully undid '{}'.\n", last_action.name());
-        success_report += &last_action.opposite().tx_success();
-    } else {
-        success_report = action.tx_success();
+    //a lenient sink may have claimed cells that were never hit
+    if let std::result::Result::Ok(Action::Sink(_)) = state.get_last_action() {
+        let never_hit = state
+            .sunk_ships()
+            .last()
+            .map_or(0, state::SunkShip::never_hit_cells);
+        if never_hit > 0 {
+            success_report += &format!(
+                "\n!!WARNING!!\nSinking this ship also marks {never_hit} cell(s) as sunk that were never hit."
+            );
+        }
     }
 
-    state.take_action(action)?;
+    if let ActionOutcome::GameOver = outcome {
+        success_report += "\nThat was the last ship!";
+    }
 
-    Ok(success_report)
+    Ok((action, success_report))
 }
 
 fn process_input(input: &str, state: &state::State) -> Result<Action> {
-    let action = parse_input(input)?;
+    let action = parse_input(
+        input,
+        state.get_shots().width(),
+        state.get_shots().height(),
+        state.index_style(),
+    )?;
 
     match action {
         //the action already has its arguments, they needn't be inferred
         Action::Fire(Known(_))
         | Action::Unfire(Known(_))
         | Action::Hit(Known(_))
+        | Action::Unhit(Known(_))
         | Action::Sink(Known(_))
         | Action::Unsink(Known(_))
-        | Action::Undo => Ok(action),
+        | Action::FireLine(Known(_))
+        | Action::UnfireLine(Known(_))
+        | Action::Undo(_) => Ok(action),
 
         //"fire" infers you meant to fire at the recommended move
-        Action::Fire(Unknown) => Ok(Action::Fire(Known(*state.get_top_moves().first().unwrap()))),
-        //"hit" infers your last "fire" was a hit
-        Action::Hit(Unknown) => {
-            if let Action::Fire(coordinates) =
-                state.get_last_matching_action(Action::Fire(Unknown))?
-            {
-                Ok(Action::Hit(coordinates))
-            } else {
-                unreachable!(
-                    "Action history returned incorrect action when asked for the last 'Fire'"
-                )
-            }
-        }
+        Action::Fire(Unknown) => Ok(Action::Fire(Known(
+            *state
+                .get_top_moves()
+                .first()
+                .ok_or_else(|| anyhow::anyhow!("There are no untested coordinates left."))?,
+        ))),
+        //"hit" infers your last "fire" was a hit.
+        //Without any "fire" yet, it was the recommended move you were about to fire at.
+        Action::Hit(Unknown) => match state.get_last_matching_action(Action::Fire(Unknown)) {
+            std::result::Result::Ok(Action::Fire(coordinates)) => Ok(Action::Hit(coordinates)),
+            std::result::Result::Ok(_) => unreachable!(
+                "Action history returned incorrect action when asked for the last 'Fire'"
+            ),
+            Err(_) => Ok(Action::Hit(Known(
+                *state
+                    .get_top_moves()
+                    .first()
+                    .ok_or_else(|| anyhow::anyhow!("There are no untested coordinates left."))?,
+            ))),
+        },
         //the "un-" actions infer you meant to undo their last opposite.
-        Action::Unfire(Unknown) | Action::Unsink(Unknown) => Ok(state
+        Action::Unfire(Unknown)
+        | Action::Unhit(Unknown)
+        | Action::Unsink(Unknown)
+        | Action::UnfireLine(Unknown) => Ok(state
             .get_last_matching_action(action.opposite())?
             .opposite()),
 
         Action::Sink(Unknown) => unreachable!("Cannot infer length of sunk ship."),
+        Action::FireLine(Unknown) => unreachable!("Cannot infer which line to fire at."),
     }
 }
 
-fn parse_input(input: &str) -> Result<Action> {
+fn parse_input(input: &str, width: usize, height: usize, style: IndexStyle) -> Result<Action> {
     let words = input.split_whitespace().collect::<Vec<&str>>();
-    let arg_count = words.len() - 1;
-
+    if words
+        .first()
+        .is_some_and(|word| word.to_lowercase() == SHOT_COMMAND)
+    {
+        return parse_shot(&words[1..], width, height, style);
+    }
     let action = parse_action(
         words
             .first()
             .ok_or_else(|| anyhow::anyhow!("Unable to parse command."))?,
     )?;
+    let arg_count = words.len() - 1;
 
     if arg_count == 0 {
         if action.can_infer_args() {
@@ -100,23 +596,29 @@ fn parse_input(input: &str) -> Result<Action> {
         return Err(anyhow::anyhow!(
             "No Arguments provided. Can't infer arguments for this action."
         ));
-    } else if arg_count != action.expected_arg_count() {
+    }
+
+    //a coordinate may also come as a single chess-style word, like "E3"
+    let is_chess_coordinate = arg_count == 1
+        && matches!(
+            action,
+            Action::Fire(_) | Action::Unfire(_) | Action::Hit(_) | Action::Unhit(_)
+        );
+    if arg_count != action.expected_arg_count() && !is_chess_coordinate {
         return Err(anyhow::anyhow!("Incorrect number of arguments."));
     }
 
     //This is awkward, but I can't blanket-assign parsed arguments to the variants, as they're of different types.
     //That could be solved with an "assign data" function, but then the compiler could not check for correct usage.
     match action {
-        Action::Fire(_) | Action::Unfire(_) | Action::Hit(_) => {
-            let coord = Known(Coordinate::from_user(
-                parse_number(words[1])?,
-                parse_number(words[2])?,
-            ));
+        Action::Fire(_) | Action::Unfire(_) | Action::Hit(_) | Action::Unhit(_) => {
+            let coord = Known(parse_coordinate(&words[1..], width, height, style)?);
 
             match action {
                 Action::Fire(_) => Ok(Action::Fire(coord)),
                 Action::Unfire(_) => Ok(Action::Unfire(coord)),
                 Action::Hit(_) => Ok(Action::Hit(coord)),
+                Action::Unhit(_) => Ok(Action::Unhit(coord)),
                 _ => unreachable!(), //the outer match arm restricts this, no need to be careful :)
             }
         }
@@ -131,7 +633,34 @@ fn parse_input(input: &str) -> Result<Action> {
             }
         }
 
-        Action::Undo => unreachable!(),
+        Action::FireLine(_) | Action::UnfireLine(_) => {
+            let line = Known(parse_line(&words[1..], width, height, style)?);
+
+            match action {
+                Action::FireLine(_) => Ok(Action::FireLine(line)),
+                Action::UnfireLine(_) => Ok(Action::UnfireLine(line)),
+                _ => unreachable!(), //same as above :)
+            }
+        }
+
+        Action::Undo(_) => Ok(Action::Undo(Known(parse_number(words[1])?))),
+    }
+}
+
+//'shot' isn't an action of its own, it resolves to whichever of 'fire' or 'hit' records the outcome in one step
+fn parse_shot(args: &[&str], width: usize, height: usize, style: IndexStyle) -> Result<Action> {
+    let (outcome, coordinate_args) = args
+        .split_last()
+        .ok_or_else(|| anyhow::anyhow!("Incorrect number of arguments."))?;
+    if !(1..=2).contains(&coordinate_args.len()) {
+        return Err(anyhow::anyhow!("Incorrect number of arguments."));
+    }
+
+    let coord = Known(parse_coordinate(coordinate_args, width, height, style)?);
+    match outcome.to_lowercase().as_str() {
+        "hit" => Ok(Action::Hit(coord)),
+        "miss" => Ok(Action::Fire(coord)),
+        _ => Err(anyhow::anyhow!("Expected either 'hit' or 'miss'.")),
     }
 }
 
@@ -146,6 +675,431 @@ fn parse_action(maybe_action: &str) -> Result<Action> {
     Err(anyhow::anyhow!("Invalid command."))
 }
 
+fn find_query(input: &str) -> Option<Query> {
+    let maybe_query_name = input.split_whitespace().next()?.to_lowercase();
+    Query::iter().find(|query| query.name() == maybe_query_name)
+}
+
+fn run_query(query: Query, input: &str, state: &mut state::State) -> Result<String> {
+    let style = state.index_style();
+    let args = input.split_whitespace().skip(1).collect::<Vec<&str>>();
+    if args.len() != query.expected_arg_count() {
+        return Err(anyhow::anyhow!("Incorrect number of arguments."));
+    }
+
+    match query {
+        Query::Prob => {
+            let coord = parse_coordinate(
+                &args,
+                state.get_shots().width(),
+                state.get_shots().height(),
+                style,
+            )?;
+            let probability = state.probability_at(coord).ok_or_else(|| {
+                anyhow::anyhow!("{} is not on the board.", coord.printable(style))
+            })?;
+
+            match state.status_at(coord) {
+                Some(ShotStatus::Untested) | None => Ok(format!(
+                    "Probability of a ship at {}: {probability:.3}",
+                    coord.printable(style)
+                )),
+                Some(status) => Ok(format!(
+                    "{} is already resolved as '{}', so its probability is 0.000.",
+                    coord.printable(style),
+                    status.name()
+                )),
+            }
+        }
+        Query::Top => {
+            let ranked_moves = state.ranked_moves(parse_number(args[0])?);
+            if ranked_moves.is_empty() {
+                return Err(anyhow::anyhow!("There are no untested coordinates left."));
+            }
+
+            Ok(ranked_moves
+                .iter()
+                .enumerate()
+                .map(|(rank, (coord, probability))| {
+                    format!("{}: {} {probability:.3}", rank + 1, coord.printable(style))
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        Query::Parity => {
+            if args[0].to_lowercase() == "show" {
+                return Ok(parity_overlay(state));
+            }
+            let parity_hunt = parse_toggle(args[0])?;
+            state.set_parity_hunt(parity_hunt);
+            Ok(format!("Parity hunting is now {}.", args[0].to_lowercase()))
+        }
+        Query::Salvo => {
+            let salvo = state.recommend_salvo();
+            if salvo.is_empty() {
+                return Err(anyhow::anyhow!("There are no untested coordinates left."));
+            }
+
+            Ok(format!(
+                "Recommended salvo: {}",
+                salvo
+                    .iter()
+                    .map(|coord| coord.printable(style))
+                    .collect::<String>()
+            ))
+        }
+        Query::Heat => {
+            if args[0].to_lowercase() != "export-csv" {
+                return Err(anyhow::anyhow!(
+                    "Unknown heat command, expected 'export-csv'."
+                ));
+            }
+
+            let mut file = std::fs::File::create(args[1])?;
+            state.get_heat_field().to_csv(&mut file)?;
+            Ok(format!("Exported probabilities to '{}'.", args[1]))
+        }
+        Query::Stats => {
+            let stats = state.stats();
+            Ok(format!(
+                "Shots fired: {}\nHits: {} ({:.1}%)\nMisses: {}\nUntested: {}\nShips sunk: {}\nShips remaining: {}",
+                stats.shots_fired,
+                stats.hits,
+                stats.hit_rate() * 100.,
+                stats.misses,
+                stats.untested,
+                stats.ships_sunk,
+                stats.ships_remaining
+            ))
+        }
+        Query::Explain => {
+            let coord = parse_coordinate(
+                &args,
+                state.get_shots().width(),
+                state.get_shots().height(),
+                style,
+            )?;
+            let explanation = state.explain(coord);
+
+            let placements = explanation
+                .placements
+                .iter()
+                .map(|(ship_length, placements)| {
+                    format!("ship {ship_length}: {placements} placements")
+                });
+            let hit_adjacent = format!(
+                "hit-adjacent: {}",
+                if explanation.hit_adjacent {
+                    "yes"
+                } else {
+                    "no"
+                }
+            );
+
+            //in the end game there's no heat to take apart
+            let layers = state
+                .base_heat()
+                .zip(state.hit_heat())
+                .zip(state.cluster_heat())
+                .map(|((base_heat, hit_heat), cluster_heat)| {
+                    format!(
+                        "base heat: {:.3}; hit heat: {:.3}; cluster heat: {:.3}",
+                        base_heat.get_grid()[coord.row][coord.column],
+                        hit_heat.get_grid()[coord.row][coord.column],
+                        cluster_heat.get_grid()[coord.row][coord.column]
+                    )
+                });
+
+            Ok(placements
+                .chain(std::iter::once(hit_adjacent))
+                .chain(layers)
+                .collect::<Vec<_>>()
+                .join("; "))
+        }
+        Query::Rules => {
+            let mut rules = state.get_rules();
+            let name = args[0].to_lowercase();
+            if name == "combine" {
+                let mode = args[1].to_lowercase();
+                rules.combine_mode = CombineMode::from_name(&mode).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unknown combine mode, expected one of: {}.",
+                        CombineMode::NAMES.join(", ")
+                    )
+                })?;
+                state.set_rules(rules);
+                return Ok(format!("Ship lengths are now combined by {mode}."));
+            }
+            let toggle = rules.toggle_mut(&name).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown rule, expected one of: {}.",
+                    Rules::NAMES.join(", ")
+                )
+            })?;
+            *toggle = parse_toggle(args[1])?;
+            state.set_rules(rules);
+            Ok(format!("Rule '{name}' is now {}.", args[1].to_lowercase()))
+        }
+        Query::Fleet => {
+            let mut ships = state.remaining_ships().to_vec();
+            if ships.is_empty() {
+                return Ok("The whole fleet is destroyed.".to_owned());
+            }
+            ships.sort_unstable_by(|a, b| b.cmp(a));
+
+            let mut groups: Vec<(usize, usize)> = vec![];
+            for ship_length in ships {
+                match groups.last_mut() {
+                    Some((length, count)) if *length == ship_length => *count += 1,
+                    _ => groups.push((ship_length, 1)),
+                }
+            }
+
+            Ok(format!(
+                "Remaining ships: {}",
+                groups
+                    .iter()
+                    .map(|(ship_length, count)| format!("{ship_length} x{count}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        }
+        Query::Diff => {
+            let saved = std::fs::read_to_string(args[0])
+                .map_err(|err| anyhow::anyhow!("Unable to read board '{}': {err}", args[0]))?;
+            let saved = parse_board(&saved)?;
+            let differences = state.get_shots().diff(&saved)?;
+            if differences.is_empty() {
+                return Ok("The boards are identical.".to_owned());
+            }
+
+            Ok(differences
+                .iter()
+                .map(|(coord, current, saved)| {
+                    format!(
+                        "{}: {} now, {} in the file",
+                        coord.printable(style),
+                        current.name(),
+                        saved.name()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        Query::History => {
+            let history = state.history_commands();
+            if history.is_empty() {
+                return Ok("No actions taken yet.".to_owned());
+            }
+
+            Ok(history
+                .iter()
+                .enumerate()
+                .map(|(idx, command)| format!("{}: {command}", idx + 1))
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        Query::SaveHistory => {
+            let history = state.export_history()?;
+            let mut file = std::fs::File::create(args[0])?;
+            for command in history {
+                writeln!(file, "{command}")?;
+            }
+            Ok(format!("Saved the history to '{}'.", args[0]))
+        }
+        Query::Percent => {
+            state.set_percent(parse_toggle(args[0])?);
+            Ok(format!("Percentages are now {}.", args[0].to_lowercase()))
+        }
+        Query::Gradient => {
+            state.set_gradient(parse_toggle(args[0])?);
+            Ok(format!(
+                "The heat gradient is now {}.",
+                args[0].to_lowercase()
+            ))
+        }
+        Query::Resize => {
+            let (width, height) = (parse_number(args[0])?, parse_number(args[1])?);
+            state.resize_board(width, height)?;
+            Ok(format!("Resized the board to {width}x{height}."))
+        }
+        Query::Simulate => {
+            let coord = parse_coordinate(
+                &args[..2],
+                state.get_shots().width(),
+                state.get_shots().height(),
+                style,
+            )?;
+            let outcome = match args[2].to_lowercase().as_str() {
+                "hit" => ShotStatus::Hit,
+                "miss" => ShotStatus::Miss,
+                _ => return Err(anyhow::anyhow!("Expected either 'hit' or 'miss'.")),
+            };
+            if state.status_at(coord) != Some(ShotStatus::Untested) {
+                return Err(anyhow::anyhow!(
+                    "{} has already been tested.",
+                    coord.printable(style)
+                ));
+            }
+
+            Ok(format!(
+                "If {} were a {}:\n{}",
+                coord.printable(style),
+                outcome.name(),
+                state.simulated_board_ascii(coord, outcome)
+            ))
+        }
+        Query::Mask => {
+            let threshold = args[0]
+                .parse::<f32>()
+                .ok()
+                .filter(|threshold| (0. ..=1.).contains(threshold))
+                .ok_or_else(|| anyhow::anyhow!("Expected a threshold between 0 and 1."))?;
+
+            Ok(state
+                .likely_mask(threshold)
+                .get_grid()
+                .iter()
+                .map(|line| {
+                    line.iter()
+                        .map(|&likely| if likely { '#' } else { '.' })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        Query::Placements => {
+            let ship_length = parse_number(args[0])?;
+            let (ship_counts, total_ship_count) = state.placements(ship_length)?;
+
+            let counts = ship_counts
+                .get_grid()
+                .iter()
+                .map(|line| {
+                    line.iter()
+                        .map(|ship_count| format!("{ship_count:>4}"))
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            Ok(format!(
+                "The ship of length {ship_length} fits {total_ship_count} ways. Placements covering each coordinate:\n{counts}"
+            ))
+        }
+        Query::Density => {
+            let axis = parse_axis(args[0])?;
+            let untested_counts = state.get_shots().map_lines(axis, |line| {
+                line.iter().filter(|status| status.is_untested()).count()
+            });
+
+            Ok(untested_counts
+                .iter()
+                .enumerate()
+                .map(|(index, untested_count)| {
+                    let line = Line { axis, index };
+                    format!("{}: {untested_count} untested", line.printable(style))
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        Query::Goto => {
+            let step = parse_number(args[0])?;
+            state.replay_to(step)?;
+            Ok(format!("Went to step {step} of {}.", state.timeline_len()))
+        }
+        #[cfg(feature = "image")]
+        Query::Render => {
+            crate::heatmap::render_png(
+                state.get_heat_field(),
+                state.get_shots(),
+                std::path::Path::new(args[0]),
+            )?;
+            Ok(format!("Rendered the board to '{}'.", args[0]))
+        }
+    }
+}
+
+//the board in its usual symbols, except untested cells on the parity grid show as '+'
+fn parity_overlay(state: &state::State) -> String {
+    state
+        .get_shots()
+        .get_grid()
+        .iter()
+        .zip(state.parity_mask().get_grid())
+        .map(|(status_line, parity_line)| {
+            status_line
+                .iter()
+                .zip(parity_line)
+                .map(|(status, &on_grid)| match status {
+                    ShotStatus::Untested if on_grid => '+',
+                    _ => status.symbol(),
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn parse_toggle(maybe_toggle: &str) -> Result<bool> {
+    match maybe_toggle.to_lowercase().as_str() {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(anyhow::anyhow!("Expected either 'on' or 'off'.")),
+    }
+}
+
+fn parse_coordinate(
+    words: &[&str],
+    width: usize,
+    height: usize,
+    style: IndexStyle,
+) -> Result<Coordinate> {
+    match *words {
+        [chess] => Coordinate::from_chess(chess, width, height),
+        //the column may be given as a letter, in which case it reads just like chess notation
+        [column, row] => match parse_number(column) {
+            std::result::Result::Ok(column) => {
+                Coordinate::from_user_checked(column, parse_number(row)?, width, height, style)
+            }
+            Err(_) => Coordinate::from_chess(&format!("{column}{row}"), width, height),
+        },
+        _ => Err(anyhow::anyhow!("Incorrect number of arguments.")),
+    }
+}
+
+//"row 3" or "column 3", counted like coordinates
+fn parse_line(words: &[&str], width: usize, height: usize, style: IndexStyle) -> Result<Line> {
+    let [axis, index] = *words else {
+        return Err(anyhow::anyhow!("Incorrect number of arguments."));
+    };
+    let axis = parse_axis(axis)?;
+    let line_count = match axis {
+        Axis::Row => height,
+        Axis::Column => width,
+    };
+    let first_index = style.first_index();
+    let last_index = line_count + first_index - 1;
+    let index = parse_number(index)?;
+    if !(first_index..=last_index).contains(&index) {
+        return Err(anyhow::anyhow!(
+            "Index must be between {first_index} and {last_index}."
+        ));
+    }
+
+    Ok(Line {
+        axis,
+        index: index - first_index,
+    })
+}
+
+fn parse_axis(word: &str) -> Result<Axis> {
+    match word.to_lowercase().as_str() {
+        "row" => Ok(Axis::Row),
+        "column" | "col" => Ok(Axis::Column),
+        _ => Err(anyhow::anyhow!("Expected either 'row' or 'column'.")),
+    }
+}
+
 fn parse_number(maybe_number: &str) -> Result<usize> {
     maybe_number
         .parse::<usize>()
@@ -153,22 +1107,438 @@ fn parse_number(maybe_number: &str) -> Result<usize> {
 }
 
 fn display_recommended_moves(state: &state::State) {
-    println!(
-        "Recommended move: {}",
-        state.get_top_moves().first().unwrap().printable()
-    );
+    let style = state.index_style();
+    let Some(top_move) = state.recommend() else {
+        println!("There are no untested coordinates left.");
+        return;
+    };
+    println!("Recommended move: {}", top_move.printable(style));
     if state.get_top_moves().len() > 1 {
         print!("Alternate moves:");
         for &coord in state.get_top_moves().iter().skip(1) {
-            print!("{}", coord.printable());
+            print!("{}", coord.printable(style));
         }
         println!();
     }
+    if state.get_rules().salvo {
+        println!(
+            "Recommended salvo: {}",
+            state
+                .recommend_salvo()
+                .iter()
+                .map(|coord| coord.printable(style))
+                .collect::<String>()
+        );
+    }
+    if state.get_rules().info_gain {
+        if let Some(&(coord, gain)) = state.info_gain_moves().first() {
+            println!(
+                "Most informative move: {} (rules out {gain:.1} placements on average)",
+                coord.printable(style)
+            );
+        }
+    }
 }
 
-fn display_help() {
+fn shot_help(style: IndexStyle) -> String {
+    format!(
+        "'shot {} <hit|miss>' {} Fires at the specified coordinate and records the outcome in one step.",
+        style.tx_coordinate_args(),
+        style.tx_coordinate_hint()
+    )
+}
+
+fn display_help(style: IndexStyle) {
     println!("Available commands:");
     for action in Action::iter() {
-        println!("{}", action.tx_syntax_help());
+        println!("{}", action.tx_syntax_help(style));
+    }
+    println!("{}", shot_help(style));
+    println!("{SETBOARD_HELP}");
+    println!("{AUTOPLAY_HELP}");
+    println!("{REPEAT_HELP}");
+    for query in Query::iter() {
+        println!("{}", query.tx_syntax_help(style));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn coord(row: usize, column: usize) -> Coordinate {
+        Coordinate { row, column }
+    }
+
+    fn new_state() -> state::State {
+        state::State::new(5, 5, &[2, 3], Rules::classic()).unwrap()
+    }
+
+    //a file only this test process uses, removed again by the caller
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("battleships-{}-{name}", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn script_plays_through_to_the_final_board() {
+        let mut state = new_state();
+        let path = temp_file("script", "fire 1 1\nhit 2 1\nhit 3 1\nsink 2\n");
+        let result = run_script(&mut state, &path, &Options::default());
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+
+        let first_row = &state.get_shots().get_grid()[0];
+        assert_eq!(
+            first_row[..4],
+            [
+                ShotStatus::Miss,
+                ShotStatus::Sunk,
+                ShotStatus::Sunk,
+                ShotStatus::Untested
+            ]
+        );
+        assert_eq!(state.remaining_ships(), [3]);
+    }
+
+    #[test]
+    fn empty_script_line_repeats_the_last_action() {
+        //where two fires in a row land, worked out on a board of its own
+        let mut expected = new_state();
+        let first_target = expected.recommend().unwrap();
+        play_round("fire", &mut expected).unwrap();
+        let second_target = expected.recommend().unwrap();
+
+        //the leading empty line has nothing to repeat yet
+        let mut state = new_state();
+        let path = temp_file("repeat", "\nfire\n\n");
+        let result = run_script(&mut state, &path, &Options::default());
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+
+        assert!(first_target != second_target);
+        assert_eq!(state.status_at(first_target), Some(ShotStatus::Miss));
+        assert_eq!(state.status_at(second_target), Some(ShotStatus::Miss));
+        assert_eq!(state.stats().misses, 2);
+    }
+
+    #[test]
+    fn script_stops_at_an_ambiguous_sink() {
+        let mut state = new_state();
+        let path = temp_file("ambiguous", "hit 1 1\nhit 2 1\nhit 3 1\nsink 2\nfire 5 5\n");
+        let result = run_script(&mut state, &path, &Options::default());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.unwrap_err().to_string().starts_with("Line 4:"));
+        assert_eq!(state.status_at(coord(4, 4)), Some(ShotStatus::Untested));
+    }
+
+    #[test]
+    fn json_report_parses_back() {
+        let mut state = new_state();
+        let result =
+            play_round("hit 2 1", &mut state).map(|(action, report)| (Some(action), report));
+        let report: serde_json::Value = serde_json::from_str(&json_report(result, &state)).unwrap();
+
+        assert!(report["ok"] == true);
+        assert!(report["action"] == "hit");
+        assert!(report["coord"][0] == 2 && report["coord"][1] == 1);
+        assert!(report["board"][0][1] == "hit");
+        assert!(report["board"][0][0] == "untested");
+        assert_eq!(report["heat"][0].as_array().unwrap().len(), 5);
+        assert!(report["top_moves"]
+            .as_array()
+            .is_some_and(|top_moves| !top_moves.is_empty()));
+        assert!(report["complete"] == false);
+    }
+
+    #[test]
+    fn json_report_of_a_failure_only_has_the_error() {
+        let mut state = new_state();
+        let result =
+            play_round("fire 9 9", &mut state).map(|(action, report)| (Some(action), report));
+        let report: serde_json::Value = serde_json::from_str(&json_report(result, &state)).unwrap();
+
+        assert!(report["ok"] == false);
+        assert!(report["error"].as_str().is_some());
+        assert!(report["board"].is_null());
+    }
+
+    #[test]
+    fn coordinates_take_a_column_letter_or_a_number() {
+        let parse = |words: &[&str]| {
+            parse_coordinate(words, 9, 7, IndexStyle::ChessOneIndexed)
+                .map(|coord| (coord.row, coord.column))
+        };
+        assert_eq!(parse(&["E", "3"]).unwrap(), (2, 4));
+        assert_eq!(parse(&["e3"]).unwrap(), (2, 4));
+        assert_eq!(parse(&["5", "3"]).unwrap(), (2, 4));
+        assert!(parse(&["J", "3"]).is_err());
+    }
+
+    #[test]
+    fn shot_records_its_outcome_as_one_undoable_action() {
+        let mut state = new_state();
+        play_round("shot 2 1 hit", &mut state).unwrap();
+        assert_eq!(state.status_at(coord(0, 1)), Some(ShotStatus::Hit));
+        assert_eq!(state.history_commands().len(), 1);
+
+        play_round("shot 3 1 miss", &mut state).unwrap();
+        assert_eq!(state.status_at(coord(0, 2)), Some(ShotStatus::Miss));
+
+        play_round("undo", &mut state).unwrap();
+        play_round("undo", &mut state).unwrap();
+        assert_eq!(state.status_at(coord(0, 1)), Some(ShotStatus::Untested));
+        assert!(state.history_commands().is_empty());
+    }
+
+    #[test]
+    fn fleet_roster_drops_sunk_ships() {
+        let mut state = state::State::new(6, 6, &[2, 3, 3, 4], Rules::classic()).unwrap();
+        assert_eq!(
+            run_query(Query::Fleet, "fleet", &mut state).unwrap(),
+            "Remaining ships: 4 x1, 3 x2, 2 x1"
+        );
+
+        for column in 0..4 {
+            play_round(&format!("hit {} 1", column + 1), &mut state).unwrap();
+        }
+        play_round("sink 4", &mut state).unwrap();
+        assert_eq!(
+            run_query(Query::Fleet, "fleet", &mut state).unwrap(),
+            "Remaining ships: 3 x2, 2 x1"
+        );
+    }
+
+    #[test]
+    fn verbose_report_follows_a_shifted_recommendation() {
+        let mut state = new_state();
+        let before = Snapshot::of(&state);
+        let top_move = state.recommend().unwrap();
+        assert!(top_move == coord(2, 2));
+
+        play_round("fire 3 3", &mut state).unwrap();
+        assert_eq!(
+            before.changes(&state),
+            "Untested cells: 25 -> 24\nRecommended move changed: [3, 3] -> [2, 2]"
+        );
+    }
+
+    #[test]
+    fn exported_history_replays_to_the_same_board() {
+        let mut state = new_state();
+        for input in [
+            "fire 1 1", "hit 2 1", "fire 5 5", "undo", "hit 3 1", "sink 2", "fire 4 4",
+        ] {
+            play_round(input, &mut state).unwrap();
+        }
+        let history = state.export_history().unwrap();
+        assert!(history.iter().all(|command| !command.starts_with("undo")));
+
+        let path = temp_file("history", &history.join("\n"));
+        let mut replayed = new_state();
+        let result = run_script(&mut replayed, &path, &Options::default());
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+
+        assert!(replayed.get_shots().equal(state.get_shots()));
+        assert_eq!(replayed.remaining_ships(), state.remaining_ships());
+    }
+
+    #[test]
+    fn protocol_exchanges_play_a_game_to_the_end() {
+        //the harness side: each ship's length and the cells not yet hit, as (column, row) like the protocol
+        let mut ships = [(2, vec![(1, 1), (2, 1)]), (3, vec![(5, 3), (5, 4), (5, 5)])];
+        let mut state = new_state();
+
+        let mut reply = protocol_reply(&state);
+        for _ in 0..25 {
+            let Some(shot) = reply.strip_prefix("FIRE ") else {
+                break;
+            };
+            let (column, row) = shot.split_once(' ').unwrap();
+            let (column, row) = (
+                column.parse::<usize>().unwrap(),
+                row.parse::<usize>().unwrap(),
+            );
+            assert!(
+                (1..=5).contains(&column) && (1..=5).contains(&row),
+                "{reply}"
+            );
+            assert_eq!(
+                state.status_at(coord(row - 1, column - 1)),
+                Some(ShotStatus::Untested),
+                "{reply}"
+            );
+
+            let outcome = match ships
+                .iter_mut()
+                .find(|(_, cells)| cells.contains(&(column, row)))
+            {
+                Some((ship_length, cells)) => {
+                    cells.retain(|&cell| cell != (column, row));
+                    if cells.is_empty() {
+                        format!("sunk:{ship_length}")
+                    } else {
+                        "hit".to_owned()
+                    }
+                }
+                None => "miss".to_owned(),
+            };
+            protocol_result(&format!("RESULT {column} {row} {outcome}"), &mut state).unwrap();
+            reply = protocol_reply(&state);
+        }
+
+        assert_eq!(reply, "DONE");
+        assert!(state.is_complete());
+    }
+
+    #[test]
+    fn protocol_rejects_malformed_results() {
+        let mut state = new_state();
+        assert!(protocol_result("RESULT 1 1", &mut state).is_err());
+        assert!(protocol_result("RESULT 6 1 miss", &mut state).is_err());
+        assert!(protocol_result("RESULT 1 1 splash", &mut state).is_err());
+        assert!(protocol_result("FIRE 1 1", &mut state).is_err());
+        assert_eq!(state.status_at(coord(0, 0)), Some(ShotStatus::Untested));
+    }
+
+    #[test]
+    fn placements_of_a_two_on_an_empty_three_by_three() {
+        let mut state = state::State::new(3, 3, &[2], Rules::classic()).unwrap();
+        let report = run_query(Query::Placements, "placements 2", &mut state).unwrap();
+        //corners are covered twice, the middle of each edge three times and the center four times
+        assert_eq!(
+            report,
+            "The ship of length 2 fits 12 ways. Placements covering each coordinate:\n   2   3   2\n   3   4   3\n   2   3   2"
+        );
+        assert!(run_query(Query::Placements, "placements 3", &mut state).is_err());
+    }
+
+    #[test]
+    fn empty_line_after_fire_fires_at_the_next_recommendation() {
+        let mut state = new_state();
+        assert!(repeated_input("\n".to_owned(), None).is_none());
+
+        let first_target = state.recommend().unwrap();
+        play_round("fire", &mut state).unwrap();
+        let second_target = state.recommend().unwrap();
+        let input = repeated_input(" \n".to_owned(), Some("fire")).unwrap();
+        assert_eq!(input, "fire");
+        play_round(&input, &mut state).unwrap();
+
+        assert!(first_target != second_target);
+        assert_eq!(state.status_at(first_target), Some(ShotStatus::Miss));
+        assert_eq!(state.status_at(second_target), Some(ShotStatus::Miss));
+        assert_eq!(
+            repeated_input("hit 1 1".to_owned(), Some("fire")).unwrap(),
+            "hit 1 1"
+        );
+    }
+
+    #[test]
+    fn bare_hit_follows_the_last_fire() {
+        let mut state = new_state();
+        play_round("fire 2 3", &mut state).unwrap();
+        play_round("hit", &mut state).unwrap();
+        assert_eq!(state.status_at(coord(2, 1)), Some(ShotStatus::Hit));
+    }
+
+    #[test]
+    fn bare_hit_without_a_fire_takes_the_top_move() {
+        let mut state = new_state();
+        let top_move = state.recommend().unwrap();
+        play_round("hit", &mut state).unwrap();
+        assert_eq!(state.status_at(top_move), Some(ShotStatus::Hit));
+        assert_eq!(state.get_shots().count_where(ShotStatus::is_hit), 1);
+    }
+
+    fn state_with_prior(name: &str, corner_weight: f32) -> state::State {
+        let mut rows = vec!["1,1,1,1,1".to_owned(); 5];
+        rows[0] = format!("{corner_weight},1,1,1,1");
+        let path = temp_file(name, &rows.join("\n"));
+        let mut state = new_state();
+        let result = load_prior(&mut state, &path);
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+        state
+    }
+
+    #[test]
+    fn prior_favoring_a_corner_draws_the_top_move_there() {
+        let uniform = state_with_prior("uniform-prior", 1.);
+        assert!(uniform.recommend() == Some(coord(2, 2)));
+
+        let cornered = state_with_prior("corner-prior", 4.);
+        assert!(cornered.recommend() == Some(coord(0, 0)));
+    }
+
+    #[test]
+    fn closed_input_ends_reading_without_an_error() {
+        assert_eq!(read_line_from(&mut "".as_bytes()).unwrap(), None);
+
+        let mut input = "fire\n".as_bytes();
+        assert_eq!(
+            read_line_from(&mut input).unwrap().as_deref(),
+            Some("fire\n")
+        );
+        assert_eq!(read_line_from(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn unreadable_input_is_an_error_not_a_panic() {
+        let err = read_line_from(&mut &b"fire \xff\xfe\n"[..]).unwrap_err();
+        assert!(err.to_string().starts_with("Unable to read input:"));
+    }
+
+    #[test]
+    fn lenient_sink_warns_in_the_report_about_cells_never_hit() {
+        let mut state = state::State::new(
+            5,
+            5,
+            &[2, 3],
+            Rules {
+                lenient_sink: true,
+                ..Rules::classic()
+            },
+        )
+        .unwrap();
+        for input in [
+            "hit 1 1", "hit 2 1", "fire 4 1", "fire 1 2", "fire 2 2", "fire 3 2",
+        ] {
+            let (_, report) = play_round(input, &mut state).unwrap();
+            assert!(!report.contains("WARNING"));
+        }
+
+        let (_, report) = play_round("sink 3", &mut state).unwrap();
+        assert!(
+            report.ends_with("Sinking this ship also marks 1 cell(s) as sunk that were never hit.")
+        );
+    }
+
+    #[test]
+    fn protocol_sink_hands_back_the_injected_resolver() {
+        let mut state = new_state();
+        let asked = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&asked);
+        state.set_location_resolver(move |_| {
+            counter.set(counter.get() + 1);
+            0
+        });
+        for result in ["RESULT 1 1 hit", "RESULT 2 1 sunk:2"] {
+            protocol_result(result, &mut state).unwrap();
+        }
+
+        //two rows of three hits leave the last ship ambiguous, which is the injected resolver's call again
+        for (row, column) in [(2, 0), (2, 1), (2, 2), (3, 0), (3, 1), (3, 2)] {
+            state
+                .take_action(Action::Hit(Known(coord(row, column))))
+                .unwrap();
+        }
+        state.take_action(Action::Sink(Known(3))).unwrap();
+        assert_eq!(asked.get(), 1);
+        assert_eq!(state.status_at(coord(2, 0)), Some(ShotStatus::Sunk));
     }
 }
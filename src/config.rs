@@ -0,0 +1,154 @@
+use crate::types::rules::{CombineMode, Rules};
+use crate::types::IndexStyle;
+use anyhow::{Ok, Result};
+use std::path::Path;
+
+/*
+* Defaults read from a TOML file, so the same setup needn't be typed out every day:
+*
+*   width = 10
+*   height = 10
+*   ships = [2, 3, 3, 4, 5]
+*   index-style = "row-major"
+*
+*   [rules]
+*   no-touch = true
+*   combine = "max"
+*
+* Every key is optional, and the rules go by the same names as the 'rules' command.
+* Flags on the command line win over the file, and '--rule <name> off' turns a rule it switched on back off.
+*/
+pub const DEFAULT_CONFIG_PATH: &str = "battleships.toml";
+
+#[derive(Default)]
+pub struct Config {
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub ships: Option<Vec<usize>>,
+    pub rules: Rules,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("Unable to read config '{}': {err}", path.display()))?;
+        Config::parse(&text)
+            .map_err(|err| anyhow::anyhow!("Invalid config '{}': {err}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Config> {
+        let table = text.parse::<toml::Table>()?;
+
+        let mut config = Config::default();
+        for (key, value) in &table {
+            match key.as_str() {
+                "width" => config.width = Some(parse_number(key, value)?),
+                "height" => config.height = Some(parse_number(key, value)?),
+                "ships" => {
+                    let ships = value.as_array().ok_or_else(|| {
+                        anyhow::anyhow!("'ships' expects a list of ship lengths.")
+                    })?;
+                    config.ships = Some(
+                        ships
+                            .iter()
+                            .map(|ship| parse_number(key, ship))
+                            .collect::<Result<_>>()?,
+                    );
+                }
+                "index-style" => {
+                    config.rules.index_style = value
+                        .as_str()
+                        .and_then(IndexStyle::from_name)
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "'index-style' expects one of: {}.",
+                                IndexStyle::NAMES.join(", ")
+                            )
+                        })?;
+                }
+                "rules" => {
+                    let rules = value
+                        .as_table()
+                        .ok_or_else(|| anyhow::anyhow!("'rules' expects a table of rules."))?;
+                    for (name, value) in rules {
+                        apply_rule(&mut config.rules, name, value)?;
+                    }
+                }
+                _ => return Err(anyhow::anyhow!("Unknown key '{key}'.")),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn parse_number(key: &str, value: &toml::Value) -> Result<usize> {
+    value
+        .as_integer()
+        .and_then(|number| usize::try_from(number).ok())
+        .ok_or_else(|| anyhow::anyhow!("'{key}' expects whole numbers of 0 or more."))
+}
+
+fn apply_rule(rules: &mut Rules, name: &str, value: &toml::Value) -> Result<()> {
+    if name == "combine" {
+        rules.combine_mode = value
+            .as_str()
+            .and_then(CombineMode::from_name)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "'combine' expects one of: {}.",
+                    CombineMode::NAMES.join(", ")
+                )
+            })?;
+        return Ok(());
+    }
+
+    let toggle = rules.toggle_mut(name).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Unknown rule '{name}', expected one of: combine, {}.",
+            Rules::NAMES.join(", ")
+        )
+    })?;
+    *toggle = value
+        .as_bool()
+        .ok_or_else(|| anyhow::anyhow!("Rule '{name}' expects true or false."))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::State;
+
+    #[test]
+    fn sample_config_sets_up_the_state() {
+        let config = Config::parse(
+            "width = 10\nheight = 8\nships = [2, 3, 5]\n\n[rules]\nno-touch = true\ncombine = \"max\"\n",
+        )
+        .unwrap();
+        assert!(config.rules.ships_must_not_touch);
+        assert!(matches!(config.rules.combine_mode, CombineMode::Max));
+
+        let state = State::new(
+            config.width.unwrap(),
+            config.height.unwrap(),
+            &config.ships.unwrap(),
+            config.rules,
+        )
+        .unwrap();
+        assert_eq!(
+            (state.get_shots().width(), state.get_shots().height()),
+            (10, 8)
+        );
+        assert_eq!(state.remaining_ships(), [2, 3, 5]);
+    }
+
+    #[test]
+    fn malformed_config_is_an_error() {
+        assert!(Config::parse("width = -3\n").is_err());
+        assert!(Config::parse("ships = 4\n").is_err());
+        assert!(Config::parse("colour = \"red\"\n").is_err());
+        assert!(Config::parse("[rules]\nno-touch = \"yes\"\n").is_err());
+        assert!(Config::parse("[rules]\nsideways = true\n").is_err());
+    }
+}
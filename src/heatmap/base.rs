@@ -1,5 +1,4 @@
 use super::*;
-use field::helpers::Axis;
 use field::Field;
 
 /*
@@ -17,53 +16,76 @@ use field::Field;
 ** meaning there is no actually good way to handle that Err() (such as an undo) even all the way at the top,
 ** all we could do is panic in automated play.
 **
-** So, my solution: Throw up a warning from right here, and just keep truckin' :)
+** So, my solution: Note down what went wrong from right here, hand it up alongside the heat, and just keep truckin' :)
+** Whoever is on top can then tell the user exactly which ship didn't fit.
 */
 
-pub(super) fn gen_heat(bool_shots: &Field<bool>, ship_lengths: &[usize]) -> Field<f32> {
-    let heat_fields = ship_lengths.iter().map(|&ship_length| {
-        let (ship_counts, total_ship_count) = gen_ship_counts(bool_shots, ship_length);
+pub(super) fn gen_heat(
+    bool_shots: &Field<bool>,
+    ship_lengths: &[usize],
+    rules: &Rules,
+    cache: &mut HeatCache,
+) -> (Field<f32>, Vec<Diagnostic>) {
+    cache.sync(bool_shots);
 
-        if total_ship_count == 0 {
-            println!("!!WARNING!!\nShip of length {ship_length} couldn't be placed a single time.");
-            println!("Something is wrong. Continuing regardless.\n###############################");
-            //Avoid the div/0
-            Field::new_default(bool_shots.width(), bool_shots.height())
-        } else {
-            ship_counts_to_heat(&ship_counts, total_ship_count)
-        }
-    });
+    //the cache needs mutable access, so the counts are gathered up front, which also tells us what didn't fit
+    let ship_counts: Vec<_> = ship_lengths
+        .iter()
+        .map(|&ship_length| {
+            let (ship_counts, total_ship_count) =
+                shape_counts(bool_shots, ShipShape::line(ship_length), cache);
+            let (ship_counts, total_ship_count) = if rules.allow_diagonal {
+                add_diagonal_counts(bool_shots, ship_length, ship_counts, total_ship_count)
+            } else {
+                (ship_counts, total_ship_count)
+            };
+            (ship_length, ship_counts, total_ship_count)
+        })
+        .collect();
 
-    reduce_heat_fields(heat_fields)
+    let diagnostics = ship_counts
+        .iter()
+        .filter(|(_, _, total_ship_count)| *total_ship_count == 0)
+        .map(|&(ship_length, _, _)| Diagnostic::NoPlacement {
+            pass: HeatPass::Base,
+            ship_length,
+            coord: None,
+        })
+        .collect();
+
+    let heat = gen_and_reduce_heat_fields(
+        ship_counts,
+        rules.combine_mode,
+        |(_, ship_counts, total_ship_count)| {
+            if total_ship_count == 0 {
+                //Avoid the div/0
+                Field::new_default(bool_shots.width(), bool_shots.height())
+            } else {
+                ship_counts_to_heat(&ship_counts, total_ship_count)
+            }
+        },
+    );
+
+    (heat, diagnostics)
+}
+
+pub(super) fn add_diagonal_counts(
+    bool_shots: &Field<bool>,
+    ship_length: usize,
+    ship_counts: Field<usize>,
+    total_ship_count: usize,
+) -> (Field<usize>, usize) {
+    let (diagonal_ship_counts, diagonal_ship_count) =
+        diagonal::gen_ship_counts(bool_shots, ship_length);
+    (
+        ship_counts.merge_field(&diagonal_ship_counts, |val, diagonal_val| {
+            val + diagonal_val
+        }),
+        total_ship_count + diagonal_ship_count,
+    )
 }
 
 fn ship_counts_to_heat(ship_counts: &Field<usize>, total_ship_count: usize) -> Field<f32> {
     let total_ship_count = total_ship_count as f32;
     ship_counts.transform_all(|&ship_count| ship_count as f32 / total_ship_count)
 }
-
-fn gen_ship_counts(bool_shots: &Field<bool>, ship_length: usize) -> (Field<usize>, usize) {
-    //because the borrow-checker doesn't like variables being captured in closures,
-    //this workaround is necessary to calm it down.
-    let total_ship_count = std::cell::RefCell::new(0);
-
-    let ship_counter = |line: Vec<bool>| {
-        let (ship_count_line, ship_count) = gen_line(&line, ship_length);
-        *total_ship_count.borrow_mut() += ship_count;
-        ship_count_line
-    };
-
-    let rows = bool_shots
-        .transform_by_line(Axis::Row, ship_counter)
-        .unwrap();
-    let columns = bool_shots
-        .transform_by_line(Axis::Column, ship_counter)
-        .unwrap();
-
-    let ship_counts = rows.merge_field(&columns, |row_val, column_val| row_val + column_val);
-
-    //dropping the refcell and returning its content
-    let total_ship_count = *total_ship_count.borrow();
-
-    (ship_counts, total_ship_count)
-}
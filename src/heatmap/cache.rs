@@ -0,0 +1,188 @@
+use super::*;
+use crate::types::Coordinate;
+use field::helpers::Axis;
+use field::Field;
+
+/*
+* A shot only ever changes a single row and column, yet the base heat used to count every line of the board,
+* for every ship length, after every action.
+* This cache remembers the placement counts of each line per ship length, and only recounts the lines
+* whose cells actually changed since the last call. Everything else is reused as is.
+*
+* Since the counts are integers, assembling them from cached lines gives the exact same result
+* as counting the whole board from scratch, which is what an empty cache does.
+*/
+#[derive(Default)]
+pub struct HeatCache {
+    //the board the cached counts belong to
+    bool_shots: Option<Field<bool>>,
+    ship_counts: Vec<(usize, LineCounts)>,
+}
+
+//placement counts and the total number of placements, for every row and column
+struct LineCounts {
+    rows: Vec<(Vec<usize>, usize)>,
+    columns: Vec<(Vec<usize>, usize)>,
+}
+
+impl HeatCache {
+    pub fn clear(&mut self) {
+        *self = HeatCache::default();
+    }
+
+    //brings all cached counts up to date with the given board, recounting only the lines that changed
+    pub(super) fn sync(&mut self, bool_shots: &Field<bool>) {
+        let Some(cached_shots) = &self.bool_shots else {
+            self.bool_shots = Some(bool_shots.clone());
+            return;
+        };
+        if cached_shots.width() != bool_shots.width()
+            || cached_shots.height() != bool_shots.height()
+        {
+            self.clear();
+            self.bool_shots = Some(bool_shots.clone());
+            return;
+        }
+
+        let mut changed_rows = vec![];
+        let mut changed_columns = vec![];
+        for (row, (cached_line, line)) in cached_shots
+            .get_grid()
+            .iter()
+            .zip(bool_shots.get_grid().iter())
+            .enumerate()
+        {
+            for (column, (cached_val, val)) in cached_line.iter().zip(line.iter()).enumerate() {
+                if cached_val != val {
+                    changed_rows.push(row);
+                    changed_columns.push(column);
+                }
+            }
+        }
+        changed_rows.dedup();
+        changed_columns.sort_unstable();
+        changed_columns.dedup();
+
+        for (ship_length, line_counts) in &mut self.ship_counts {
+            for &row in &changed_rows {
+                line_counts.rows[row] = count_line(bool_shots, Axis::Row, row, *ship_length);
+            }
+            for &column in &changed_columns {
+                line_counts.columns[column] =
+                    count_line(bool_shots, Axis::Column, column, *ship_length);
+            }
+        }
+
+        self.bool_shots = Some(bool_shots.clone());
+    }
+
+    //Expects the cache to be synced with the given board. Only placements within lines of the given axes count,
+    //so [Axis::Row] leaves out the vertical ones.
+    pub(super) fn ship_counts(
+        &mut self,
+        bool_shots: &Field<bool>,
+        ship_length: usize,
+        axes: &[Axis],
+    ) -> (Field<usize>, usize) {
+        let line_counts = match self
+            .ship_counts
+            .iter()
+            .position(|(cached_length, _)| *cached_length == ship_length)
+        {
+            Some(idx) => &self.ship_counts[idx].1,
+            None => {
+                let line_counts = LineCounts {
+                    rows: bool_shots
+                        .map_lines(Axis::Row, |line| placement_counts(line, ship_length)),
+                    columns: bool_shots
+                        .map_lines(Axis::Column, |line| placement_counts(line, ship_length)),
+                };
+                self.ship_counts.push((ship_length, line_counts));
+                &self.ship_counts.last().unwrap().1
+            }
+        };
+
+        let lines_of = |axis: Axis| match axis {
+            Axis::Row => &line_counts.rows,
+            Axis::Column => &line_counts.columns,
+        };
+
+        let mut ship_counts = Field::new_default(bool_shots.width(), bool_shots.height());
+        for row in 0..bool_shots.height() {
+            for column in 0..bool_shots.width() {
+                let coord = Coordinate { row, column };
+                let ship_count = axes
+                    .iter()
+                    .map(|&axis| {
+                        lines_of(axis)[coord.get_axis_index(axis)].0
+                            [coord.get_axis_index(axis.opposite())]
+                    })
+                    .sum::<usize>();
+                ship_counts.set_value(coord, &ship_count).unwrap();
+            }
+        }
+
+        let total_ship_count = axes
+            .iter()
+            .flat_map(|&axis| lines_of(axis).iter())
+            .map(|(_, ship_count)| ship_count)
+            .sum();
+
+        (ship_counts, total_ship_count)
+    }
+}
+
+fn count_line(
+    bool_shots: &Field<bool>,
+    axis: Axis,
+    index: usize,
+    ship_length: usize,
+) -> (Vec<usize>, usize) {
+    placement_counts(&bool_shots.get_line(axis, index).unwrap(), ship_length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ShotStatus;
+
+    fn bits(heat_field: &Field<f32>) -> Vec<u32> {
+        heat_field
+            .get_grid()
+            .iter()
+            .flatten()
+            .map(|heat| heat.to_bits())
+            .collect()
+    }
+
+    #[test]
+    fn cached_heat_matches_a_full_recompute_bit_for_bit() {
+        let ship_lengths = [2, 3, 3, 4, 5];
+        let rules = Rules::classic();
+        let mut shots: Field<ShotStatus> = Field::new_default(20, 20);
+        let mut cache = HeatCache::default();
+
+        //a spread of misses, a few hits and a sunk ship, one cell at a time
+        let shots_taken = (0..40)
+            .map(|step| {
+                let coord = Coordinate {
+                    row: (step * 7) % 20,
+                    column: (step * 13) % 20,
+                };
+                let status = match step % 10 {
+                    3 => ShotStatus::Hit,
+                    7 => ShotStatus::Sunk,
+                    _ => ShotStatus::Miss,
+                };
+                (coord, status)
+            })
+            .collect::<Vec<_>>();
+
+        for (coord, status) in shots_taken {
+            shots.set_value(coord, &status).unwrap();
+            let (cached, _) = gen_heat_field_cached(&shots, &ship_lengths, &rules, &mut cache);
+            let recomputed = gen_heat_field(&shots, &ship_lengths, &rules);
+            assert_eq!(bits(&cached), bits(&recomputed));
+        }
+    }
+}
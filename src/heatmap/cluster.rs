@@ -0,0 +1,114 @@
+use crate::types::field_ext::FieldExt;
+use crate::types::{AxisExt, Coordinate, ShotStatus, Step};
+use field::{helpers::Axis, Field};
+
+//Two hits in a row almost certainly belong to the same ship, so the cells continuing that row are very likely hits too.
+const EXTENSION_HEAT: f32 = 0.9;
+
+//orthogonally connected groups of hits
+pub fn hit_clusters(shots: &Field<ShotStatus>) -> Vec<Vec<Coordinate>> {
+    shots.connected_components(ShotStatus::is_hit, false)
+}
+
+pub(super) fn gen_heat(shots: &Field<ShotStatus>, ship_lengths: &[usize]) -> Field<f32> {
+    let mut heat = Field::new_default(shots.width(), shots.height());
+
+    for cluster in hit_clusters(shots) {
+        //a run as long as every ship still afloat can't grow any further
+        if !ship_lengths.iter().any(|&length| length > cluster.len()) {
+            continue;
+        }
+        for extension in line_extensions(&cluster) {
+            let extension_is_open = shots.contains_coord(extension)
+                && shots.get_grid()[extension.row][extension.column].is_untested();
+            if extension_is_open {
+                heat.set_value(extension, &EXTENSION_HEAT).unwrap();
+            }
+        }
+    }
+
+    heat
+}
+
+//the cells on either end of a straight cluster, which may lie outside the board.
+//single hits and bent clusters (several ships touching) have no clear direction, so they get none.
+fn line_extensions(cluster: &[Coordinate]) -> Vec<Coordinate> {
+    if cluster.len() < 2 {
+        return vec![];
+    }
+    let first = cluster[0];
+
+    //the axis whose index stays the same along the whole cluster, the cluster extends along the other one
+    let Some(fixed_axis) = Axis::all().into_iter().find(|&axis| {
+        cluster
+            .iter()
+            .all(|coord| coord.get_axis_index(axis) == first.get_axis_index(axis))
+    }) else {
+        return vec![];
+    };
+    let axis = fixed_axis.opposite();
+
+    let start = cluster
+        .iter()
+        .min_by_key(|coord| coord.get_axis_index(axis))
+        .unwrap();
+    let end = cluster
+        .iter()
+        .max_by_key(|coord| coord.get_axis_index(axis))
+        .unwrap();
+
+    let mut extensions = vec![];
+    extensions.extend(start.step(axis, -1));
+    extensions.extend(end.step(axis, 1));
+
+    extensions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coord(row: usize, column: usize) -> Coordinate {
+        Coordinate { row, column }
+    }
+
+    fn board_with_hits(hits: &[Coordinate]) -> Field<ShotStatus> {
+        let mut shots = Field::new_default(6, 6);
+        for &hit in hits {
+            shots.set_value(hit, &ShotStatus::Hit).unwrap();
+        }
+        shots
+    }
+
+    #[test]
+    fn clusters_group_orthogonally_touching_hits() {
+        let shots = board_with_hits(&[
+            coord(1, 1),
+            coord(1, 2),
+            coord(2, 2),
+            coord(4, 4),
+            coord(5, 5),
+        ]);
+        let mut sizes = hit_clusters(&shots)
+            .iter()
+            .map(Vec::len)
+            .collect::<Vec<_>>();
+        sizes.sort_unstable();
+        assert_eq!(sizes, [1, 1, 3]);
+    }
+
+    #[test]
+    fn a_run_boosts_both_ends_while_a_longer_ship_remains() {
+        let mut shots = board_with_hits(&[coord(2, 2), coord(2, 3)]);
+        shots.set_value(coord(2, 1), &ShotStatus::Miss).unwrap();
+
+        let heat = gen_heat(&shots, &[2, 3]);
+        assert_eq!(heat.get_grid()[2][4], EXTENSION_HEAT);
+        //blocked by the miss
+        assert_eq!(heat.get_grid()[2][1], 0.);
+        assert_eq!(heat.get_grid()[1][2], 0.);
+
+        //nothing left afloat is longer than the run
+        assert_eq!(gen_heat(&shots, &[2]).get_grid()[2][4], 0.);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::types::{Coordinate, IndexStyle, Printable};
+
+//which of the heat layers ran into the problem
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeatPass {
+    Base,
+    Hit,
+}
+
+//A sign that the board can't be right, see the comment at the top of base.rs for why these don't stop anything.
+#[derive(Clone, Copy)]
+pub enum Diagnostic {
+    NoPlacement {
+        pass: HeatPass,
+        ship_length: usize,
+        //the hit no ship fits through. The base pass looks at the whole board, so it has none.
+        coord: Option<Coordinate>,
+    },
+    //more hits on the board than the ships still afloat have cells
+    TooManyHits {
+        hits: usize,
+        tonnage: usize,
+    },
+}
+
+impl Diagnostic {
+    pub fn tx_message(&self, style: IndexStyle) -> String {
+        match *self {
+            Diagnostic::NoPlacement {
+                ship_length,
+                coord: Some(coord),
+                ..
+            } => format!(
+                "Not even the ship of length {ship_length} fits through the hit at {}.",
+                coord.printable(style)
+            ),
+            Diagnostic::NoPlacement {
+                ship_length,
+                coord: None,
+                ..
+            } => format!("Ship of length {ship_length} couldn't be placed a single time."),
+            Diagnostic::TooManyHits { hits, tonnage } => {
+                format!(
+                    "The board is impossible: {hits} hits, but only {tonnage} ship cells remain."
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heatmap::{gen_heat_field_cached, HeatCache};
+    use crate::types::{rules::Rules, ShotStatus};
+    use field::Field;
+
+    #[test]
+    fn names_the_ship_length_that_cannot_fit() {
+        //a miss on every diagonal cell leaves no room for five in a row, but plenty for two
+        let mut shots = Field::new_default(5, 5);
+        for idx in 0..5 {
+            shots
+                .set_value(
+                    Coordinate {
+                        row: idx,
+                        column: idx,
+                    },
+                    &ShotStatus::Miss,
+                )
+                .unwrap();
+        }
+
+        let (_, diagnostics) = gen_heat_field_cached(
+            &shots,
+            &[2, 5],
+            &Rules::classic(),
+            &mut HeatCache::default(),
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert!(matches!(
+            diagnostics[0],
+            Diagnostic::NoPlacement {
+                pass: HeatPass::Base,
+                ship_length: 5,
+                coord: None
+            }
+        ));
+        assert_eq!(
+            diagnostics[0].tx_message(IndexStyle::ChessOneIndexed),
+            "Ship of length 5 couldn't be placed a single time."
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use super::*;
+use crate::types::Coordinate;
+use field::Field;
+
+/*
+* The field crate only knows about rows and columns, so diagonal lines are assembled here by hand.
+* Unlike rows and columns, diagonals vary in length: from a single corner cell
+* up to the shorter of the two board dimensions. Line indices run from one corner to the opposite one.
+*/
+#[derive(Clone, Copy)]
+pub(super) enum Diagonal {
+    //top-left to bottom-right, line 0 is the bottom-left corner
+    Down,
+    //bottom-left to top-right, line 0 is the top-left corner
+    Up,
+}
+
+impl Diagonal {
+    pub(super) fn all() -> [Diagonal; 2] {
+        [Diagonal::Down, Diagonal::Up]
+    }
+
+    pub(super) fn number_of_lines(width: usize, height: usize) -> usize {
+        width + height - 1
+    }
+
+    pub(super) fn line_coords(self, width: usize, height: usize, index: usize) -> Vec<Coordinate> {
+        match self {
+            Diagonal::Down => {
+                let (start_row, start_column) = if index < height {
+                    (height - 1 - index, 0)
+                } else {
+                    (0, index + 1 - height)
+                };
+                (0..)
+                    .map(|step| Coordinate {
+                        row: start_row + step,
+                        column: start_column + step,
+                    })
+                    .take_while(|coord| coord.row < height && coord.column < width)
+                    .collect()
+            }
+            Diagonal::Up => {
+                let (start_row, start_column) = if index < height {
+                    (index, 0)
+                } else {
+                    (height - 1, index + 1 - height)
+                };
+                (0..)
+                    .map_while(|step| {
+                        Some(Coordinate {
+                            row: start_row.checked_sub(step)?,
+                            column: start_column + step,
+                        })
+                    })
+                    .take_while(|coord| coord.column < width)
+                    .collect()
+            }
+        }
+    }
+
+    //returns the index of the line the coordinate lies on, and its position within that line
+    pub(super) fn line_through(self, height: usize, coord: Coordinate) -> (usize, usize) {
+        match self {
+            Diagonal::Down => (
+                coord.column + height - 1 - coord.row,
+                coord.row.min(coord.column),
+            ),
+            Diagonal::Up => {
+                let index = coord.row + coord.column;
+                (index, index.min(height - 1) - coord.row)
+            }
+        }
+    }
+}
+
+pub(super) fn gen_ship_counts(
+    bool_shots: &Field<bool>,
+    ship_length: usize,
+) -> (Field<usize>, usize) {
+    let (width, height) = (bool_shots.width(), bool_shots.height());
+    let mut ship_counts = Field::new_default(width, height);
+    let mut total_ship_count = 0;
+
+    for diagonal in Diagonal::all() {
+        for index in 0..Diagonal::number_of_lines(width, height) {
+            let coords = diagonal.line_coords(width, height, index);
+            let (ship_count_line, ship_count) =
+                placement_counts(&get_values(bool_shots, &coords), ship_length);
+
+            add_values(&mut ship_counts, &coords, &ship_count_line);
+            total_ship_count += ship_count;
+        }
+    }
+
+    (ship_counts, total_ship_count)
+}
+
+//every way a line of the given length can lie along a diagonal, each with its cells in line order
+pub fn diagonal_footprints(
+    width: usize,
+    height: usize,
+    ship_length: usize,
+) -> Vec<Vec<Coordinate>> {
+    Diagonal::all()
+        .into_iter()
+        .flat_map(|diagonal| {
+            (0..Diagonal::number_of_lines(width, height))
+                .map(move |index| diagonal.line_coords(width, height, index))
+        })
+        .flat_map(|coords| {
+            coords
+                .windows(ship_length)
+                .map(<[Coordinate]>::to_vec)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+pub(super) fn get_values<T: Copy>(field: &Field<T>, coords: &[Coordinate]) -> Vec<T> {
+    coords
+        .iter()
+        .map(|coord| field.get_grid()[coord.row][coord.column])
+        .collect()
+}
+
+pub(super) fn add_values<T: Copy + std::ops::Add<Output = T>>(
+    field: &mut Field<T>,
+    coords: &[Coordinate],
+    values: &[T],
+) {
+    for (&coord, &value) in coords.iter().zip(values) {
+        let current = field.get_grid()[coord.row][coord.column];
+        field.set_value(coord, &(current + value)).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //everything a miss, except the given cells
+    fn open_cells(width: usize, height: usize, cells: &[Coordinate]) -> Field<ShotStatus> {
+        let mut shots = Field::new_default(width, height);
+        shots.transform_all_mut(|status| *status = ShotStatus::Miss);
+        for &coord in cells {
+            shots.set_value(coord, &ShotStatus::Untested).unwrap();
+        }
+        shots
+    }
+
+    #[test]
+    fn free_diagonal_counts_like_a_free_row() {
+        let length = 5;
+        let rules = Rules {
+            allow_diagonal: true,
+            ..Rules::classic()
+        };
+        let diagonal_cells = (0..length)
+            .map(|step| Coordinate {
+                row: step,
+                column: step,
+            })
+            .collect::<Vec<_>>();
+        let row_cells = (0..length)
+            .map(|column| Coordinate { row: 0, column })
+            .collect::<Vec<_>>();
+
+        for ship_length in 2..=length {
+            let (diagonal_counts, diagonal_total) = placement_field(
+                &open_cells(length, length, &diagonal_cells),
+                ship_length,
+                &rules,
+            );
+            let (row_counts, row_total) = placement_field(
+                &open_cells(length, length, &row_cells),
+                ship_length,
+                &Rules::classic(),
+            );
+
+            assert_eq!(diagonal_total, row_total);
+            assert_eq!(
+                get_values(&diagonal_counts, &diagonal_cells),
+                get_values(&row_counts, &row_cells)
+            );
+        }
+    }
+
+    #[test]
+    fn diagonals_vary_in_length_and_map_back() {
+        let (width, height) = (4, 3);
+        for diagonal in Diagonal::all() {
+            let lengths = (0..Diagonal::number_of_lines(width, height))
+                .map(|index| diagonal.line_coords(width, height, index).len())
+                .collect::<Vec<_>>();
+            assert_eq!(lengths, vec![1, 2, 3, 3, 2, 1]);
+
+            for index in 0..Diagonal::number_of_lines(width, height) {
+                for (position, &coord) in diagonal
+                    .line_coords(width, height, index)
+                    .iter()
+                    .enumerate()
+                {
+                    assert_eq!(diagonal.line_through(height, coord), (index, position));
+                }
+            }
+        }
+    }
+}
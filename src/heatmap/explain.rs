@@ -0,0 +1,98 @@
+use super::*;
+use crate::types::Coordinate;
+use field::Field;
+
+/*
+* The heat field only tells you how likely a cell is, not why.
+* This takes the heat generation apart for a single cell, so a player can follow the solver's reasoning.
+*/
+pub struct CellExplanation {
+    //(ship length, placements covering the cell), each remaining length once, longest first
+    pub placements: Vec<(usize, usize)>,
+    //whether the cell gets extra heat from a nearby hit
+    pub hit_adjacent: bool,
+}
+
+pub fn explain_cell(
+    shots: &Field<ShotStatus>,
+    ship_lengths: &[usize],
+    rules: &Rules,
+    coord: Coordinate,
+) -> CellExplanation {
+    if ship_lengths.is_empty() {
+        //the whole fleet is sunk, so no ship can explain any heat
+        return CellExplanation {
+            placements: vec![],
+            hit_adjacent: false,
+        };
+    }
+
+    let bool_shots = gen_bool_shots(shots, rules);
+
+    let mut unique_lengths = ship_lengths.to_vec();
+    unique_lengths.sort_unstable_by(|a, b| b.cmp(a));
+    unique_lengths.dedup();
+
+    let mut cache = HeatCache::default();
+    cache.sync(&bool_shots);
+    let placements = unique_lengths
+        .iter()
+        .map(|&ship_length| {
+            let (mut ship_counts, total_ship_count) =
+                shape_counts(&bool_shots, ShipShape::line(ship_length), &mut cache);
+            if rules.allow_diagonal {
+                (ship_counts, _) = base::add_diagonal_counts(
+                    &bool_shots,
+                    ship_length,
+                    ship_counts,
+                    total_ship_count,
+                );
+            }
+            (ship_length, ship_counts.get_grid()[coord.row][coord.column])
+        })
+        .collect();
+
+    let hits = shots.find_all(ShotStatus::is_hit);
+    let (hit_heat, _) = hit::gen_heat(&bool_shots, &hits, ship_lengths, rules);
+    let hit_adjacent = shots.get_grid()[coord.row][coord.column].is_untested()
+        && hit_heat.get_grid()[coord.row][coord.column] > 0.;
+
+    CellExplanation {
+        placements,
+        hit_adjacent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CENTER: Coordinate = Coordinate { row: 2, column: 2 };
+
+    #[test]
+    fn counts_the_placements_through_a_central_cell() {
+        let mut shots = Field::new_default(5, 5);
+        let explanation = explain_cell(&shots, &[2, 3, 3, 5], &Rules::classic(), CENTER);
+        assert_eq!(explanation.placements, [(5, 2), (3, 6), (2, 4)]);
+        assert!(!explanation.hit_adjacent);
+
+        shots
+            .set_value(Coordinate { row: 2, column: 3 }, &ShotStatus::Hit)
+            .unwrap();
+        shots
+            .set_value(Coordinate { row: 1, column: 2 }, &ShotStatus::Miss)
+            .unwrap();
+        let explanation = explain_cell(&shots, &[2, 3, 3, 5], &Rules::classic(), CENTER);
+        //the miss above leaves only the vertical placements running downwards
+        assert_eq!(explanation.placements, [(5, 1), (3, 4), (2, 3)]);
+        assert!(explanation.hit_adjacent);
+    }
+
+    #[test]
+    fn nothing_to_explain_without_ships() {
+        let shots = Field::new_default(5, 5);
+        let explanation = explain_cell(&shots, &[], &Rules::classic(), CENTER);
+        assert!(explanation.placements.is_empty());
+        assert!(!explanation.hit_adjacent);
+    }
+}
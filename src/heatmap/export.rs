@@ -0,0 +1,78 @@
+use crate::types::field_ext::FieldExt;
+use field::Field;
+use std::io::{self, Write};
+
+pub trait ToCsv {
+    fn to_csv(&self, writer: &mut impl Write) -> io::Result<()>;
+}
+
+impl ToCsv for Field<f32> {
+    //one grid row per line, so a spreadsheet gets the same shape as the board
+    fn to_csv(&self, writer: &mut impl Write) -> io::Result<()> {
+        for row in self.get_grid() {
+            let line = row
+                .iter()
+                .map(|heat| format!("{heat:.5}"))
+                .collect::<Vec<_>>()
+                .join(",");
+            writeln!(writer, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+pub trait FromCsv: Sized {
+    fn from_csv(text: &str) -> anyhow::Result<Self>;
+}
+
+impl FromCsv for Field<f32> {
+    //the same shape to_csv writes. Blank lines are skipped.
+    fn from_csv(text: &str) -> anyhow::Result<Field<f32>> {
+        let rows = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(line_idx, line)| {
+                line.split(',')
+                    .map(|value| {
+                        value.trim().parse::<f32>().map_err(|_| {
+                            anyhow::anyhow!(
+                                "Row {}: unable to read '{}' as a number.",
+                                line_idx + 1,
+                                value.trim()
+                            )
+                        })
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let width = rows.first().map_or(0, Vec::len);
+        if let Some(line_idx) = rows.iter().position(|row| row.len() != width) {
+            return Err(anyhow::anyhow!(
+                "Row {} has {} values, expected {width} like the first one.",
+                line_idx + 1,
+                rows[line_idx].len()
+            ));
+        }
+
+        let height = rows.len();
+        Field::from_flat(rows.into_iter().flatten().collect(), width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_one_row_per_line() {
+        let heat_field = Field::from_flat(vec![0.25, 0., 1., 0.123456], 2, 2).unwrap();
+        let mut csv = vec![];
+        heat_field.to_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "0.25000,0.00000\n1.00000,0.12346\n"
+        );
+    }
+}
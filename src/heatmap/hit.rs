@@ -1,49 +1,131 @@
+use super::diagonal::{self, Diagonal};
 use super::*;
 use crate::types::Coordinate;
-use field::{helpers::Axis, Field};
+use field::Field;
 
 pub(super) fn gen_heat(
     bool_shots: &Field<bool>,
     hits: &[Coordinate],
     ship_lengths: &[usize],
-) -> Field<f32> {
+    rules: &Rules,
+) -> (Field<f32>, Vec<Diagnostic>) {
     if hits.is_empty() {
         //there are no hits to work on, just return all 0.s
-        return Field::new_default(bool_shots.width(), bool_shots.height());
+        return (
+            Field::new_default(bool_shots.width(), bool_shots.height()),
+            vec![],
+        );
     }
 
-    let heat_fields = ship_lengths
+    //any ship that fits through a hit leaves room for a shorter one, so only the shortest needs checking
+    let shortest = *ship_lengths.iter().min().unwrap();
+    let diagnostics = hits
         .iter()
-        .map(|&ship_length| gen_ship_heat(bool_shots, hits, ship_length));
+        .filter(|&&hit| count_through_hit(bool_shots, hit, shortest, rules.allow_diagonal).1 == 0)
+        .map(|&hit| Diagnostic::NoPlacement {
+            pass: HeatPass::Hit,
+            ship_length: shortest,
+            coord: Some(hit),
+        })
+        .collect();
 
-    reduce_heat_fields(heat_fields)
+    let heat =
+        gen_and_reduce_heat_fields(ship_lengths.to_vec(), rules.combine_mode, |ship_length| {
+            gen_ship_heat(bool_shots, hits, ship_length, rules.allow_diagonal)
+        });
+
+    (heat, diagnostics)
 }
 
-fn gen_ship_heat(bool_shots: &Field<bool>, hits: &[Coordinate], ship_length: usize) -> Field<f32> {
+fn gen_ship_heat(
+    bool_shots: &Field<bool>,
+    hits: &[Coordinate],
+    ship_length: usize,
+    allow_diagonal: bool,
+) -> Field<f32> {
     let mut heat = Field::new_default(bool_shots.width(), bool_shots.height());
     for &hit in hits {
-        let (row, column) = bool_shots.get_lines_context(hit).unwrap();
+        let (lines, total_ship_count) =
+            count_through_hit(bool_shots, hit, ship_length, allow_diagonal);
 
-        let (row_ship_count_line, row_ship_count) =
-            gen_line(&mask_around_hit(&row, hit.column, ship_length), ship_length);
-        let (column_ship_count_line, column_ship_count) =
-            gen_line(&mask_around_hit(&column, hit.row, ship_length), ship_length);
+        if total_ship_count != 0 {
+            for (line, ship_count_line) in &lines {
+                let line_heat = ship_counts_to_heat(ship_count_line, total_ship_count);
+                line.add_values(&mut heat, &line_heat);
+            }
+        }
+        //should there be no ships, avoid the div/0. gen_heat already reports it.
+    }
 
-        let total_ship_count = row_ship_count + column_ship_count;
+    heat.transform_all_mut(|val| *val /= hits.len() as f32);
+    heat
+}
 
-        if total_ship_count != 0 {
-            let row_heat = ship_counts_to_heat(&row_ship_count_line, total_ship_count);
-            let column_heat = ship_counts_to_heat(&column_ship_count_line, total_ship_count);
+//A line through a hit. Rows and columns are addressed on the field directly,
+//diagonals aren't lines to the field crate, so they come as their cells.
+enum HitLine {
+    Straight(Axis, usize),
+    Diagonal(Vec<Coordinate>),
+}
 
-            heat.merge_line(Axis::Row, hit.row, &row_heat, |acc, e| acc + e)
-                .unwrap();
-            heat.merge_line(Axis::Column, hit.column, &column_heat, |acc, e| acc + e)
-                .unwrap();
+impl HitLine {
+    fn get_values(&self, bool_shots: &Field<bool>) -> Vec<bool> {
+        match self {
+            HitLine::Straight(axis, index) => bool_shots.get_line(*axis, *index).unwrap(),
+            HitLine::Diagonal(coords) => diagonal::get_values(bool_shots, coords),
         }
-        //should there be no ships, avoid the div/0 but otherwise don't worry about it
     }
 
-    heat.transform_all(|val| val / hits.len() as f32)
+    fn add_values(&self, heat: &mut Field<f32>, values: &[f32]) {
+        match self {
+            HitLine::Straight(axis, index) => heat
+                .update_line(*axis, *index, |val, position| *val += values[position])
+                .unwrap(),
+            HitLine::Diagonal(coords) => diagonal::add_values(heat, coords, values),
+        }
+    }
+}
+
+//a line, and how many placements cover each of its cells
+type LineCounts = (HitLine, Vec<usize>);
+
+//the placements of the ship through the hit, per line the hit lies on, and how many there are in total
+fn count_through_hit(
+    bool_shots: &Field<bool>,
+    hit: Coordinate,
+    ship_length: usize,
+    allow_diagonal: bool,
+) -> (Vec<LineCounts>, usize) {
+    let (width, height) = (bool_shots.width(), bool_shots.height());
+
+    let mut lines = vec![
+        (HitLine::Straight(Axis::Row, hit.row), hit.column),
+        (HitLine::Straight(Axis::Column, hit.column), hit.row),
+    ];
+    if allow_diagonal {
+        for diagonal in Diagonal::all() {
+            let (index, position) = diagonal.line_through(height, hit);
+            lines.push((
+                HitLine::Diagonal(diagonal.line_coords(width, height, index)),
+                position,
+            ));
+        }
+    }
+
+    let mut total_ship_count = 0;
+    let lines = lines
+        .into_iter()
+        .map(|(line, position)| {
+            let (ship_count_line, ship_count) = placement_counts(
+                &mask_around_hit(&line.get_values(bool_shots), position, ship_length),
+                ship_length,
+            );
+            total_ship_count += ship_count;
+            (line, ship_count_line)
+        })
+        .collect();
+
+    (lines, total_ship_count)
 }
 
 fn ship_counts_to_heat(ship_counts: &[usize], total_ship_count: usize) -> Vec<f32> {
@@ -1,29 +1,216 @@
 mod base;
+mod cache;
+mod cluster;
+mod diagnostic;
+mod diagonal;
+mod explain;
+mod export;
 mod hit;
+#[cfg(feature = "image")]
+mod render;
 
-use crate::types::ShotStatus;
-use field::Field;
+pub use cache::HeatCache;
+pub use cluster::hit_clusters;
+pub use diagnostic::{Diagnostic, HeatPass};
+pub use diagonal::diagonal_footprints;
+pub use explain::{explain_cell, CellExplanation};
+pub use export::{FromCsv, ToCsv};
+#[cfg(feature = "image")]
+pub use render::render_png;
 
-pub fn gen_heat_field(shots: &Field<ShotStatus>, ship_lengths: &[usize]) -> Field<f32> {
-    let bool_shots = shots.transform_all(|&status| status.can_contain_ship());
-    let hits = shots.find_all(|status| {
-        std::mem::discriminant(status) == std::mem::discriminant(&ShotStatus::Hit)
-    });
+use crate::types::field_ext::FieldExt;
+use crate::types::rules::{CombineMode, Rules};
+use crate::types::ship_shape::ShipShape;
+use crate::types::{Coordinate, ShotStatus};
+use field::{helpers::Axis, Field};
 
-    let base_heat = base::gen_heat(&bool_shots, ship_lengths);
-    let hit_heat = hit::gen_heat(&bool_shots, &hits, ship_lengths);
+pub fn gen_heat_field(
+    shots: &Field<ShotStatus>,
+    ship_lengths: &[usize],
+    rules: &Rules,
+) -> Field<f32> {
+    gen_heat_field_cached(shots, ship_lengths, rules, &mut HeatCache::default()).0
+}
+
+//same as gen_heat_field, but reuses whatever placement counts are still valid from the previous call.
+//Also hands back everything about the board that looked inconsistent along the way.
+pub fn gen_heat_field_cached(
+    shots: &Field<ShotStatus>,
+    ship_lengths: &[usize],
+    rules: &Rules,
+    cache: &mut HeatCache,
+) -> (Field<f32>, Vec<Diagnostic>) {
+    let (breakdown, diagnostics) = gen_heat_field_detailed(shots, ship_lengths, rules, cache);
+    (breakdown.combined, diagnostics)
+}
+
+//the layers that make up the heat, before they're combined and masked
+pub struct HeatBreakdown {
+    pub base: Field<f32>,
+    pub hit: Field<f32>,
+    pub cluster: Field<f32>,
+    //base, hit and cluster combined as independent chances and masked, which is what gen_heat_field returns.
+    //The prior isn't part of it, State weighs that in afterwards.
+    pub combined: Field<f32>,
+}
+
+//same as gen_heat_field_cached, but keeps each layer around as well
+pub fn gen_heat_field_detailed(
+    shots: &Field<ShotStatus>,
+    ship_lengths: &[usize],
+    rules: &Rules,
+    cache: &mut HeatCache,
+) -> (HeatBreakdown, Vec<Diagnostic>) {
+    if ship_lengths.is_empty() {
+        //the whole fleet is sunk, there is nothing left to find
+        let empty = Field::new_default(shots.width(), shots.height());
+        return (
+            HeatBreakdown {
+                base: empty.clone(),
+                hit: empty.clone(),
+                cluster: empty.clone(),
+                combined: empty,
+            },
+            vec![],
+        );
+    }
+
+    let bool_shots = gen_bool_shots(shots, rules);
+    let hits = shots.find_all(ShotStatus::is_hit);
+
+    let (base_heat, mut diagnostics) = base::gen_heat(&bool_shots, ship_lengths, rules, cache);
+    let (hit_heat, hit_diagnostics) = hit::gen_heat(&bool_shots, &hits, ship_lengths, rules);
+    diagnostics.extend(hit_diagnostics);
+
+    let cluster_heat = cluster::gen_heat(shots, ship_lengths);
+
+    //the layers are separate pieces of evidence, however the ship lengths were combined
+    let combined_heat = reduce_heat_fields(
+        [base_heat.clone(), hit_heat.clone(), cluster_heat.clone()].into_iter(),
+        CombineMode::Product,
+    );
+
+    (
+        HeatBreakdown {
+            base: base_heat,
+            hit: hit_heat,
+            cluster: cluster_heat,
+            combined: mask_heat_field(&combined_heat, shots),
+        },
+        diagnostics,
+    )
+}
+
+//how many ways there are to place each remaining ship on its own, added up over the fleet
+pub fn count_placements(shots: &Field<ShotStatus>, ship_lengths: &[usize], rules: &Rules) -> usize {
+    ship_lengths
+        .iter()
+        .map(|&ship_length| placement_field(shots, ship_length, rules).1)
+        .sum()
+}
+
+//how many placements of the ship cover each cell, and how many there are in total. The counts behind the base heat.
+pub fn placement_field(
+    shots: &Field<ShotStatus>,
+    ship_length: usize,
+    rules: &Rules,
+) -> (Field<usize>, usize) {
+    let bool_shots = gen_bool_shots(shots, rules);
+    let mut cache = HeatCache::default();
+    cache.sync(&bool_shots);
+
+    let (ship_counts, total_ship_count) =
+        shape_counts(&bool_shots, ShipShape::line(ship_length), &mut cache);
+    if rules.allow_diagonal {
+        base::add_diagonal_counts(&bool_shots, ship_length, ship_counts, total_ship_count)
+    } else {
+        (ship_counts, total_ship_count)
+    }
+}
+
+//true wherever a ship could still be
+fn gen_bool_shots(shots: &Field<ShotStatus>, rules: &Rules) -> Field<bool> {
+    let mut bool_shots = shots.transform_all(|&status| status.can_contain_ship());
+    if rules.ships_must_not_touch {
+        block_touching_cells(&mut bool_shots, shots);
+    }
+    bool_shots
+}
+
+fn block_touching_cells(bool_shots: &mut Field<bool>, shots: &Field<ShotStatus>) {
+    //a sunk ship is complete, so nothing else may sit anywhere around it
+    let around_sunk = shots
+        .find_all(ShotStatus::is_sunk)
+        .into_iter()
+        .flat_map(|coord| [shots.neighbors(coord), shots.neighbors_diagonal(coord)].concat());
+    //a hit ship still grows along its line, but its corners belong to nobody
+    let around_hits = shots
+        .find_all(ShotStatus::is_hit)
+        .into_iter()
+        .flat_map(|coord| shots.neighbors_diagonal(coord));
+
+    for neighbor in around_sunk.chain(around_hits) {
+        //only untested cells are affected, hits in particular must stay placeable
+        if shots.get_grid()[neighbor.row][neighbor.column].is_untested() {
+            bool_shots.set_value(neighbor, &false).unwrap();
+        }
+    }
+}
+
+fn reduce_heat_fields(fields: impl Iterator<Item = Field<f32>>, mode: CombineMode) -> Field<f32> {
+    let combined = fields
+        .map(|field| prepare_heat_field(field, mode))
+        .reduce(|acc, e| merge_heat_fields(&acc, &e, mode))
+        .unwrap();
+    finish_heat_field(combined, mode)
+}
+
+//Product treats the fields as independent chances, so it multiplies the chances of a cell being empty.
+fn prepare_heat_field(mut field: Field<f32>, mode: CombineMode) -> Field<f32> {
+    match mode {
+        CombineMode::Product => field.transform_all_mut(|val| *val = 1. - *val),
+        CombineMode::Max | CombineMode::Sum => {}
+    }
+    field
+}
+
+fn merge_heat_fields(acc: &Field<f32>, e: &Field<f32>, mode: CombineMode) -> Field<f32> {
+    match mode {
+        CombineMode::Product => acc.merge_field(e, |acc_val, e_val| acc_val * e_val),
+        CombineMode::Max => acc.merge_field(e, |acc_val, e_val| acc_val.max(*e_val)),
+        CombineMode::Sum => acc.merge_field(e, |acc_val, e_val| acc_val + e_val),
+    }
+}
 
-    let combined_heat = reduce_heat_fields([base_heat, hit_heat].into_iter());
+fn finish_heat_field(mut field: Field<f32>, mode: CombineMode) -> Field<f32> {
+    match mode {
+        CombineMode::Product => field.transform_all_mut(|val| *val = 1. - *val),
+        CombineMode::Max | CombineMode::Sum => {}
+    }
+    field
+}
 
-    mask_heat_field(&combined_heat, shots)
+//generates one heat field per item and reduces them like reduce_heat_fields.
+//with the rayon feature, the fields are generated on all cores, but still combined one after another in item order.
+//Float rounding depends on that order, and the heat should come out bit for bit the same either way.
+#[cfg(not(feature = "rayon"))]
+fn gen_and_reduce_heat_fields<I, F>(items: Vec<I>, mode: CombineMode, gen_heat: F) -> Field<f32>
+where
+    F: Fn(I) -> Field<f32>,
+{
+    reduce_heat_fields(items.into_iter().map(gen_heat), mode)
 }
 
-fn reduce_heat_fields(fields: impl Iterator<Item = Field<f32>>) -> Field<f32> {
-    fields
-        .map(|field| field.transform_all(|val| 1. - val))
-        .reduce(|acc, e| acc.merge_field(&e, |acc_val, e_val| acc_val * e_val))
-        .unwrap()
-        .transform_all(|val| 1. - val)
+#[cfg(feature = "rayon")]
+fn gen_and_reduce_heat_fields<I, F>(items: Vec<I>, mode: CombineMode, gen_heat: F) -> Field<f32>
+where
+    I: Send,
+    F: Fn(I) -> Field<f32> + Sync + Send,
+{
+    use rayon::prelude::*;
+
+    let fields = items.into_par_iter().map(gen_heat).collect::<Vec<_>>();
+    reduce_heat_fields(fields.into_iter(), mode)
 }
 
 fn mask_heat_field(heat: &Field<f32>, shots: &Field<ShotStatus>) -> Field<f32> {
@@ -33,7 +220,72 @@ fn mask_heat_field(heat: &Field<f32>, shots: &Field<ShotStatus>) -> Field<f32> {
     })
 }
 
-fn gen_line(shots: &[bool], ship_length: usize) -> (Vec<usize>, usize) {
+//the cells a ship of the given length covers, starting at start and running along the axis the way Step does,
+//so Axis::Column gives a horizontal ship. Whether they all lie on the board is up to the caller.
+pub fn placement_cells(start: Coordinate, axis: Axis, ship_length: usize) -> Vec<Coordinate> {
+    (0..ship_length)
+        .map(|offset| {
+            let mut coord = start;
+            coord.set_axis_index(axis, start.get_axis_index(axis) + offset);
+            coord
+        })
+        .collect()
+}
+
+//the cells of a footprint with its top left corner at the given coordinate, row by row
+pub fn footprint_cells(corner: Coordinate, width: usize, height: usize) -> Vec<Coordinate> {
+    (0..height)
+        .flat_map(|row| {
+            (0..width).map(move |column| Coordinate {
+                row: corner.row + row,
+                column: corner.column + column,
+            })
+        })
+        .collect()
+}
+
+//How many placements of the footprint cover each cell, and how many there are in total. Lines take the cached
+//line by line path, wider shapes get every position on the board tried. Diagonal placements only exist for lines.
+//Expects the cache to be synced with the given board.
+pub fn shape_counts(
+    bool_shots: &Field<bool>,
+    shape: ShipShape,
+    cache: &mut HeatCache,
+) -> (Field<usize>, usize) {
+    if shape.is_line() {
+        //a horizontal line lies within a row, a vertical one within a column
+        let axes = match (shape.rotatable, shape.height) {
+            (true, _) => vec![Axis::Row, Axis::Column],
+            (false, 1) => vec![Axis::Row],
+            (false, _) => vec![Axis::Column],
+        };
+        return cache.ship_counts(bool_shots, shape.cell_count(), &axes);
+    }
+
+    let mut ship_counts = Field::new_default(bool_shots.width(), bool_shots.height());
+    let mut total_ship_count = 0;
+    for (width, height) in shape.orientations() {
+        for (corner, window) in bool_shots.windows_2d(width, height) {
+            if window.iter().flatten().all(|&&free| free) {
+                total_ship_count += 1;
+                for coord in footprint_cells(corner, width, height) {
+                    let ship_count = ship_counts.get_grid()[coord.row][coord.column];
+                    ship_counts.set_value(coord, &(ship_count + 1)).unwrap();
+                }
+            }
+        }
+    }
+
+    (ship_counts, total_ship_count)
+}
+
+//for every cell of the line, how many placements of the ship cover it, and how many placements there are in total.
+//true marks a cell a ship could occupy.
+pub fn placement_counts(shots: &[bool], ship_length: usize) -> (Vec<usize>, usize) {
+    if shots.is_empty() {
+        return (vec![], 0);
+    }
+
     let mut result = Vec::with_capacity(shots.len());
     let mut ship_count = 0;
 
@@ -83,3 +335,380 @@ fn gen_free_space(space: usize, ship_length: usize) -> (Vec<usize>, usize) {
         ship_count,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    //float rounding may push a product a hair past the bounds, but never further
+    const EPSILON: f32 = 1e-6;
+
+    //any mix of shots on a board of up to 10x10, row by row, and a fleet of up to five ships of length 1 to 5.
+    //Nothing keeps the board consistent with the fleet, the heat has to stay sane either way.
+    //Kept as plain values rather than a Field, so a shrunk failure prints as a readable board.
+    fn board_and_fleet() -> impl Strategy<Value = (usize, usize, Vec<ShotStatus>, Vec<usize>)> {
+        (1..=10usize, 1..=10usize).prop_flat_map(|(width, height)| {
+            let statuses = prop::sample::select(vec![
+                ShotStatus::Untested,
+                ShotStatus::Miss,
+                ShotStatus::Hit,
+                ShotStatus::Sunk,
+            ]);
+            (
+                Just(width),
+                Just(height),
+                prop::collection::vec(statuses, width * height),
+                prop::collection::vec(1..=5usize, 0..=5),
+            )
+        })
+    }
+
+    fn rules(ships_must_not_touch: bool, allow_diagonal: bool) -> Rules {
+        Rules {
+            ships_must_not_touch,
+            allow_diagonal,
+            ..Rules::classic()
+        }
+    }
+
+    fn board_with_sunk_ship() -> Field<ShotStatus> {
+        let mut shots = Field::new_default(5, 5);
+        for column in 0..2 {
+            shots
+                .set_value(Coordinate { row: 0, column }, &ShotStatus::Sunk)
+                .unwrap();
+        }
+        shots
+    }
+
+    #[test]
+    fn no_touch_rules_out_placements_next_to_a_sunk_ship() {
+        let shots = board_with_sunk_ship();
+        let no_touch = rules(true, false);
+
+        let (_, free_count) = placement_field(&shots, 2, &Rules::classic());
+        let (ship_counts, no_touch_count) = placement_field(&shots, 2, &no_touch);
+        assert_eq!(free_count, 36);
+        assert_eq!(no_touch_count, 28);
+        assert_eq!(ship_counts.get_grid()[1][0], 0);
+        assert_eq!(ship_counts.get_grid()[0][2], 0);
+        assert_eq!(count_placements(&shots, &[2, 3], &no_touch), 28 + 18);
+    }
+
+    #[test]
+    fn empty_square_board_is_symmetric_under_rotation() {
+        let shots = Field::new_default(7, 7);
+        let heat_field = gen_heat_field(&shots, &[2, 3, 3, 4, 5], &Rules::classic());
+        let rotated = heat_field.rotate_90();
+
+        for (&heat, &rotated_heat) in heat_field
+            .get_grid()
+            .iter()
+            .flatten()
+            .zip(rotated.get_grid().iter().flatten())
+        {
+            assert!((heat - rotated_heat).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn parallel_reduction_matches_the_sequential_one() {
+        let mut shots = board_with_sunk_ship();
+        for column in [1, 3] {
+            shots
+                .set_value(Coordinate { row: 3, column }, &ShotStatus::Miss)
+                .unwrap();
+        }
+        shots
+            .set_value(Coordinate { row: 2, column: 2 }, &ShotStatus::Hit)
+            .unwrap();
+        let gen_heat = |ship_length: usize| {
+            let (ship_counts, total_ship_count) =
+                placement_field(&shots, ship_length, &Rules::classic());
+            ship_counts.transform_all(|&count| count as f32 / total_ship_count as f32)
+        };
+        let bits = |field: Field<f32>| {
+            field
+                .get_grid()
+                .iter()
+                .flatten()
+                .map(|heat| heat.to_bits())
+                .collect::<Vec<_>>()
+        };
+
+        let ship_lengths = vec![2, 3, 3, 4, 5];
+        for mode in [CombineMode::Product, CombineMode::Max, CombineMode::Sum] {
+            let sequential = reduce_heat_fields(
+                ship_lengths
+                    .iter()
+                    .map(|&ship_length| gen_heat(ship_length)),
+                mode,
+            );
+            let parallel = gen_and_reduce_heat_fields(ship_lengths.clone(), mode, gen_heat);
+            assert_eq!(bits(parallel), bits(sequential));
+        }
+    }
+
+    #[test]
+    fn placement_counts_of_an_empty_line() {
+        assert_eq!(placement_counts(&[], 3), (vec![], 0));
+    }
+
+    #[test]
+    fn placement_counts_of_an_open_line() {
+        assert_eq!(placement_counts(&[true; 5], 3), (vec![1, 2, 3, 2, 1], 3));
+        assert_eq!(placement_counts(&[true; 5], 1), (vec![1; 5], 5));
+        assert_eq!(placement_counts(&[true; 5], 5), (vec![1; 5], 1));
+        //min(i + 1, L - i, ship length, placements)
+        let (counts, total) = placement_counts(&[true; 9], 4);
+        assert_eq!(counts, [1, 2, 3, 4, 4, 4, 3, 2, 1]);
+        assert_eq!(total, 6);
+    }
+
+    #[test]
+    fn placement_counts_around_obstacles() {
+        let line = [true, true, false, true, true, true, false];
+        assert_eq!(placement_counts(&line, 2), (vec![1, 1, 0, 1, 2, 1, 0], 3));
+        assert_eq!(placement_counts(&line, 3), (vec![0, 0, 0, 1, 1, 1, 0], 1));
+        assert_eq!(placement_counts(&[false; 4], 1), (vec![0; 4], 0));
+    }
+
+    #[test]
+    fn placement_counts_of_a_ship_longer_than_the_line() {
+        assert_eq!(placement_counts(&[true; 3], 4), (vec![0; 3], 0));
+    }
+
+    #[test]
+    fn classic_rules_keep_the_plain_heat() {
+        let shots = Field::new_default(3, 1);
+        let heat_field = gen_heat_field(&shots, &[2], &Rules::classic());
+        assert_eq!(heat_field.get_grid()[0], [0.5, 1., 0.5]);
+
+        let mut shots = Field::new_default(9, 7);
+        shots
+            .set_value(Coordinate { row: 3, column: 4 }, &ShotStatus::Hit)
+            .unwrap();
+        shots
+            .set_value(Coordinate { row: 3, column: 5 }, &ShotStatus::Miss)
+            .unwrap();
+        let bits = |rules: &Rules| {
+            gen_heat_field(&shots, &[2, 3, 3, 4, 5], rules)
+                .get_grid()
+                .iter()
+                .flatten()
+                .map(|heat| heat.to_bits())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(bits(&Rules::classic()), bits(&Rules::default()));
+    }
+
+    //order of the three cells by heat, hottest first
+    fn ranking(heat: &Field<f32>) -> Vec<usize> {
+        let row = &heat.get_grid()[0];
+        let mut columns = (0..row.len()).collect::<Vec<_>>();
+        columns.sort_by(|&a, &b| row[b].total_cmp(&row[a]));
+        columns
+    }
+
+    #[test]
+    fn combine_modes_rank_the_same_chances_differently() {
+        //per ship length: two even chances, one strong one, and three weak ones
+        let length_heat = || {
+            [
+                Field::from_flat(vec![0.5, 0.7, 0.3], 3, 1).unwrap(),
+                Field::from_flat(vec![0.5, 0., 0.3], 3, 1).unwrap(),
+                Field::from_flat(vec![0., 0., 0.3], 3, 1).unwrap(),
+            ]
+            .into_iter()
+        };
+
+        let product = reduce_heat_fields(length_heat(), CombineMode::Product);
+        let max = reduce_heat_fields(length_heat(), CombineMode::Max);
+        let sum = reduce_heat_fields(length_heat(), CombineMode::Sum);
+        assert_eq!(ranking(&product), [0, 1, 2]);
+        assert_eq!(ranking(&max), [1, 0, 2]);
+        assert_eq!(ranking(&sum), [0, 2, 1]);
+        assert!((product.get_grid()[0][2] - (1. - 0.7f32.powi(3))).abs() < EPSILON);
+    }
+
+    #[test]
+    fn product_is_still_the_default_combine_mode() {
+        let shots = board_with_sunk_ship();
+        let heat = |combine_mode| {
+            let rules = Rules {
+                combine_mode,
+                ..Rules::classic()
+            };
+            gen_heat_field(&shots, &[2, 3], &rules)
+        };
+
+        let default_heat = gen_heat_field(&shots, &[2, 3], &Rules::default());
+        assert!(heat(CombineMode::Product).equal(&default_heat));
+        assert!(!heat(CombineMode::Max).equal(&default_heat));
+        assert!(!heat(CombineMode::Sum).equal(&default_heat));
+    }
+
+    fn pairs(cells: Vec<Coordinate>) -> Vec<(usize, usize)> {
+        cells
+            .into_iter()
+            .map(|coord| (coord.row, coord.column))
+            .collect()
+    }
+
+    #[test]
+    fn placement_cells_run_along_the_axis() {
+        let start = Coordinate { row: 1, column: 2 };
+        assert_eq!(
+            pairs(placement_cells(start, Axis::Column, 3)),
+            [(1, 2), (1, 3), (1, 4)]
+        );
+        assert_eq!(
+            pairs(placement_cells(start, Axis::Row, 3)),
+            [(1, 2), (2, 2), (3, 2)]
+        );
+    }
+
+    #[test]
+    fn placement_cells_leave_the_board_edge_to_the_caller() {
+        //on a 5 wide board, the last two cells are already off it
+        let start = Coordinate { row: 0, column: 3 };
+        let cells = placement_cells(start, Axis::Column, 4);
+        assert_eq!(pairs(cells.clone()), [(0, 3), (0, 4), (0, 5), (0, 6)]);
+
+        let shots: Field<ShotStatus> = Field::new_default(5, 5);
+        assert_eq!(
+            cells
+                .into_iter()
+                .filter(|&coord| shots.contains_coord(coord))
+                .count(),
+            2
+        );
+    }
+
+    fn counts_of_shape(shots: &Field<ShotStatus>, shape: ShipShape) -> (Field<usize>, usize) {
+        let bool_shots = gen_bool_shots(shots, &Rules::classic());
+        let mut cache = HeatCache::default();
+        cache.sync(&bool_shots);
+        shape_counts(&bool_shots, shape, &mut cache)
+    }
+
+    #[test]
+    fn upright_line_shape_counts_like_a_ship_length() {
+        let shots = board_with_sunk_ship();
+        let upright = ShipShape {
+            width: 1,
+            height: 3,
+            rotatable: true,
+        };
+
+        let (shape_counts, shape_total) = counts_of_shape(&shots, upright);
+        let (length_counts, length_total) = placement_field(&shots, 3, &Rules::classic());
+        assert_eq!(shape_total, length_total);
+        assert!(shape_counts.equal(&length_counts));
+    }
+
+    #[test]
+    fn fixed_shapes_only_lie_one_way() {
+        let shots = Field::new_default(4, 4);
+        let lying = ShipShape {
+            width: 3,
+            height: 1,
+            rotatable: false,
+        };
+        let (ship_counts, total_ship_count) = counts_of_shape(&shots, lying);
+        assert_eq!(total_ship_count, 8);
+        assert!(ship_counts
+            .get_grid()
+            .iter()
+            .all(|line| line[..] == [1, 2, 2, 1]));
+
+        let base = ShipShape {
+            width: 2,
+            height: 2,
+            rotatable: true,
+        };
+        assert_eq!(counts_of_shape(&shots, base).1, 9);
+    }
+
+    #[test]
+    fn combined_heat_is_the_reduction_of_the_layers() {
+        let mut shots = board_with_sunk_ship();
+        for (row, column) in [(2, 2), (2, 3), (4, 0)] {
+            shots
+                .set_value(Coordinate { row, column }, &ShotStatus::Hit)
+                .unwrap();
+        }
+        shots
+            .set_value(Coordinate { row: 3, column: 3 }, &ShotStatus::Miss)
+            .unwrap();
+        let ship_lengths = [3, 4];
+
+        let (breakdown, _) = gen_heat_field_detailed(
+            &shots,
+            &ship_lengths,
+            &Rules::classic(),
+            &mut HeatCache::default(),
+        );
+        let reduced = reduce_heat_fields(
+            [breakdown.base, breakdown.hit, breakdown.cluster].into_iter(),
+            CombineMode::Product,
+        );
+        assert!(mask_heat_field(&reduced, &shots).equal(&breakdown.combined));
+        assert!(gen_heat_field(&shots, &ship_lengths, &Rules::classic()).equal(&breakdown.combined));
+    }
+
+    //locks the signature the heat code leans on: the two fields and the result may each hold a different type
+    #[test]
+    fn merge_field_combines_fields_of_different_types() {
+        let counts: Field<usize> = Field::from_flat(vec![0, 1, 2, 3, 4, 5], 3, 2).unwrap();
+        let open: Field<bool> =
+            Field::from_flat(vec![true, false, true, true, true, false], 3, 2).unwrap();
+
+        let merged: Field<f32> =
+            counts.merge_field(
+                &open,
+                |&count, &is_open| {
+                    if is_open {
+                        count as f32 / 2.
+                    } else {
+                        -1.
+                    }
+                },
+            );
+
+        assert_eq!(merged.to_flat(), (vec![0., -1., 1., 1.5, 2., -1.], 3, 2));
+    }
+
+    proptest! {
+        #[test]
+        fn heat_stays_within_bounds(
+            (width, height, statuses, ship_lengths) in board_and_fleet(),
+            ships_must_not_touch in prop::bool::ANY,
+            allow_diagonal in prop::bool::ANY,
+        ) {
+            let shots = Field::from_flat(statuses, width, height).unwrap();
+            let heat_field =
+                gen_heat_field(&shots, &ship_lengths, &rules(ships_must_not_touch, allow_diagonal));
+            for &heat in heat_field.get_grid().iter().flatten() {
+                prop_assert!((-EPSILON..=1. + EPSILON).contains(&heat), "heat {heat} is out of bounds");
+            }
+        }
+
+        #[test]
+        fn resolved_cells_have_no_heat(
+            (width, height, statuses, ship_lengths) in board_and_fleet(),
+            ships_must_not_touch in prop::bool::ANY,
+            allow_diagonal in prop::bool::ANY,
+        ) {
+            let shots = Field::from_flat(statuses, width, height).unwrap();
+            let heat_field =
+                gen_heat_field(&shots, &ship_lengths, &rules(ships_must_not_touch, allow_diagonal));
+            for (status, &heat) in shots.get_grid().iter().flatten().zip(heat_field.get_grid().iter().flatten()) {
+                if !status.is_untested() {
+                    prop_assert_eq!(heat, 0.);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::types::ShotStatus;
+use anyhow::{Ok, Result};
+use field::Field;
+use image::{ImageFormat, Rgb, RgbImage};
+use std::path::Path;
+
+//side length of the square drawn for every cell, in pixels
+const CELL_SIZE: u32 = 32;
+
+const MISS_COLOR: Rgb<u8> = Rgb([128, 128, 128]);
+const HIT_COLOR: Rgb<u8> = Rgb([0, 0, 0]);
+const SUNK_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+
+pub fn render_png(heat: &Field<f32>, shots: &Field<ShotStatus>, path: &Path) -> Result<()> {
+    let mut image = RgbImage::new(
+        heat.width() as u32 * CELL_SIZE,
+        heat.height() as u32 * CELL_SIZE,
+    );
+
+    for (row_idx, (heat_line, status_line)) in heat
+        .get_grid()
+        .iter()
+        .zip(shots.get_grid().iter())
+        .enumerate()
+    {
+        for (column_idx, (&heat_val, status)) in
+            heat_line.iter().zip(status_line.iter()).enumerate()
+        {
+            let color = match status {
+                ShotStatus::Untested => heat_to_color(heat_val),
+                ShotStatus::Miss => MISS_COLOR,
+                ShotStatus::Hit => HIT_COLOR,
+                ShotStatus::Sunk => SUNK_COLOR,
+            };
+
+            let (left, top) = (column_idx as u32 * CELL_SIZE, row_idx as u32 * CELL_SIZE);
+            for y in top..top + CELL_SIZE {
+                for x in left..left + CELL_SIZE {
+                    image.put_pixel(x, y, color);
+                }
+            }
+        }
+    }
+
+    image.save_with_format(path, ImageFormat::Png)?;
+    Ok(())
+}
+
+//blue for cold cells, fading to red for hot ones
+fn heat_to_color(heat: f32) -> Rgb<u8> {
+    let heat = heat.clamp(0., 1.);
+    Rgb([
+        (heat * 255.).round() as u8,
+        0,
+        ((1. - heat) * 255.).round() as u8,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_a_png_with_a_block_per_cell() {
+        let (width, height) = (3, 2);
+        let heat = Field::new_default(width, height);
+        let shots = Field::new_default(width, height);
+        let path =
+            std::env::temp_dir().join(format!("battleships-render-{}.png", std::process::id()));
+
+        render_png(&heat, &shots, &path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let dimensions = image::image_dimensions(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(image::guess_format(&bytes).unwrap(), ImageFormat::Png);
+        assert_eq!(
+            dimensions,
+            (width as u32 * CELL_SIZE, height as u32 * CELL_SIZE)
+        );
+    }
+}
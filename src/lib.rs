@@ -0,0 +1,4 @@
+#![allow(dead_code, unused_variables)]
+//the heat generation and the types it works on, so the benches can reach them. Everything interactive stays in the binary.
+pub mod heatmap;
+pub mod types;
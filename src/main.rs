@@ -1,16 +1,73 @@
 #![allow(dead_code, unused_variables)]
 //#![warn(clippy::pedantic)]
+use battleships::{heatmap, types};
+
 mod cli;
-mod heatmap;
+mod config;
+mod options;
 mod state;
-mod types;
 
 const DEFAULT_HEIGHT: usize = 7;
 const DEFAULT_WIDTH: usize = 9;
 const DEFAULT_SHIPS: [usize; 5] = [2, 3, 3, 4, 5];
 
 fn main() {
-    let mut state = state::State::new(DEFAULT_WIDTH, DEFAULT_HEIGHT, &DEFAULT_SHIPS);
+    let options = match options::Options::from_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+
+    let width = options.width.unwrap_or(DEFAULT_WIDTH);
+    let height = options.height.unwrap_or(DEFAULT_HEIGHT);
+    let ships = options.ships.clone().unwrap_or(DEFAULT_SHIPS.to_vec());
+    let mut state = match state::State::new(width, height, &ships, options.rules) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+    state.set_auto_sink(options.auto_sink);
+    state.set_max_turns(options.max_turns);
+    state.set_log(options.log.clone());
+    #[cfg(feature = "rand")]
+    if let Some(seed) = options.seed {
+        state.set_seed(seed);
+    }
+    if options.plain {
+        colored::control::set_override(false);
+        state.set_plain(true);
+    }
+
+    if let Some(board_file) = &options.board_file {
+        if let Err(err) = cli::load_truth(&mut state, board_file) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(prior) = &options.prior {
+        if let Err(err) = cli::load_prior(&mut state, prior) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(script) = &options.script {
+        if let Err(err) = cli::run_script(&mut state, script, &options) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        if !options.interactive {
+            return;
+        }
+    }
 
-    cli::main_loop(&mut state);
+    if let Err(err) = cli::main_loop(&mut state, &options) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,228 @@
+use crate::config::{Config, DEFAULT_CONFIG_PATH};
+use crate::types::rules::{CombineMode, Rules};
+use crate::types::IndexStyle;
+use anyhow::{Ok, Result};
+use std::path::PathBuf;
+
+#[derive(Default)]
+pub struct Options {
+    pub rules: Rules,
+    //board size and fleet from the flags or the config file, the built-in defaults apply to whatever they leave out
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub ships: Option<Vec<usize>>,
+    pub auto_sink: bool,
+    pub script: Option<PathBuf>,
+    //the real layout to play against, so fires resolve to hits or misses by themselves
+    pub board_file: Option<PathBuf>,
+    //weights for the heat, in the CSV shape 'heat export-csv' writes
+    pub prior: Option<PathBuf>,
+    //every action taken is appended to this file, one JSON object per line
+    pub log: Option<PathBuf>,
+    pub interactive: bool,
+    pub json: bool,
+    //speaks the tournament line protocol instead, see protocol_loop
+    pub protocol: bool,
+    //no colors, for redirected output and terminals without ANSI support
+    pub plain: bool,
+    //after every action, report how the board and the recommendation changed
+    pub verbose: bool,
+    //picks among equally good moves pseudo-randomly, but the same way every run
+    #[cfg(feature = "rand")]
+    pub seed: Option<u64>,
+    //automated play gives up after this many turns, instead of the board size plus some slack
+    pub max_turns: Option<usize>,
+}
+
+impl Options {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Options> {
+        let mut options = Options::default();
+        let args = args.into_iter().collect::<Vec<_>>();
+
+        //the config file goes first, so the flags below can override it
+        let config_path = match args.iter().position(|arg| arg == "--config") {
+            Some(idx) => {
+                Some(PathBuf::from(args.get(idx + 1).ok_or_else(|| {
+                    anyhow::anyhow!("'--config' expects a file path.")
+                })?))
+            }
+            None => Some(PathBuf::from(DEFAULT_CONFIG_PATH)).filter(|path| path.exists()),
+        };
+        if let Some(path) = config_path {
+            let config = Config::load(&path)?;
+            options.rules = config.rules;
+            options.width = config.width;
+            options.height = config.height;
+            options.ships = config.ships;
+        }
+
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--no-touch" => options.rules.ships_must_not_touch = true,
+                "--diagonal" => options.rules.allow_diagonal = true,
+                "--parity" => options.rules.parity_hunt = true,
+                "--salvo" => options.rules.salvo = true,
+                "--lenient-sink" => options.rules.lenient_sink = true,
+                "--center" => options.rules.center_tie_break = true,
+                "--info-gain" => options.rules.info_gain = true,
+                //switches a rule either way, so one the config turned on can be turned off again
+                "--rule" => {
+                    let (Some(name), Some(toggle)) = (args.next(), args.next()) else {
+                        return Err(anyhow::anyhow!(
+                            "'--rule' expects a rule name and either 'on' or 'off'."
+                        ));
+                    };
+                    let rule = options.rules.toggle_mut(&name).ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Unknown rule '{name}', expected one of: {}.",
+                            Rules::NAMES.join(", ")
+                        )
+                    })?;
+                    *rule = match toggle.to_lowercase().as_str() {
+                        "on" => true,
+                        "off" => false,
+                        _ => {
+                            return Err(anyhow::anyhow!(
+                                "'--rule {name}' expects either 'on' or 'off'."
+                            ))
+                        }
+                    };
+                }
+                "--auto-sink" => options.auto_sink = true,
+                "--width" => options.width = Some(next_number(&mut args, "--width")?),
+                "--height" => options.height = Some(next_number(&mut args, "--height")?),
+                "--ships" => {
+                    let ships = args.next().ok_or_else(|| {
+                        anyhow::anyhow!("'--ships' expects ship lengths separated by commas.")
+                    })?;
+                    options.ships = Some(
+                        ships
+                            .split(',')
+                            .map(|ship| {
+                                ship.trim().parse().map_err(|_| {
+                                    anyhow::anyhow!(
+                                        "Unable to read '{ship}' as a ship length, expected a number."
+                                    )
+                                })
+                            })
+                            .collect::<Result<_>>()?,
+                    );
+                }
+                //already loaded above
+                "--config" => {
+                    args.next();
+                }
+                "--script" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("'--script' expects a file path."))?;
+                    options.script = Some(PathBuf::from(path));
+                }
+                "--board-file" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("'--board-file' expects a file path."))?;
+                    options.board_file = Some(PathBuf::from(path));
+                }
+                "--prior" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("'--prior' expects a file path."))?;
+                    options.prior = Some(PathBuf::from(path));
+                }
+                "--log" => {
+                    let path = args
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("'--log' expects a file path."))?;
+                    options.log = Some(PathBuf::from(path));
+                }
+                "--combine" => {
+                    let name = args.next().ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "'--combine' expects one of: {}.",
+                            CombineMode::NAMES.join(", ")
+                        )
+                    })?;
+                    options.rules.combine_mode =
+                        CombineMode::from_name(&name).ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "Unknown combine mode '{name}', expected one of: {}.",
+                                CombineMode::NAMES.join(", ")
+                            )
+                        })?;
+                }
+                "--index-style" => {
+                    let name = args.next().ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "'--index-style' expects one of: {}.",
+                            IndexStyle::NAMES.join(", ")
+                        )
+                    })?;
+                    options.rules.index_style = IndexStyle::from_name(&name).ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Unknown index style '{name}', expected one of: {}.",
+                            IndexStyle::NAMES.join(", ")
+                        )
+                    })?;
+                }
+                #[cfg(feature = "rand")]
+                "--seed" => {
+                    let seed = args
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("'--seed' expects a number."))?;
+                    options.seed = Some(seed.parse().map_err(|_| {
+                        anyhow::anyhow!("Unable to read '{seed}' as a seed, expected a number.")
+                    })?);
+                }
+                "--max-turns" => {
+                    let max_turns = args
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("'--max-turns' expects a number."))?;
+                    options.max_turns = Some(max_turns.parse().map_err(|_| {
+                        anyhow::anyhow!(
+                            "Unable to read '{max_turns}' as a turn count, expected a number."
+                        )
+                    })?);
+                }
+                "--interactive" => options.interactive = true,
+                "--json" => options.json = true,
+                "--protocol" => options.protocol = true,
+                "--plain" => options.plain = true,
+                "--verbose" => options.verbose = true,
+                _ => return Err(anyhow::anyhow!("Unknown argument '{arg}'.")),
+            }
+        }
+
+        //see https://no-color.org
+        if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            options.plain = true;
+        }
+
+        Ok(options)
+    }
+}
+
+fn next_number(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<usize> {
+    let number = args
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("'{flag}' expects a number."))?;
+    number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Unable to read '{number}' for '{flag}', expected a number."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options> {
+        Options::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn plain_flag_turns_off_colors() {
+        assert!(parse(&["--plain"]).unwrap().plain);
+    }
+}
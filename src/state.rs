@@ -1,135 +1,960 @@
-use field::{Field, helpers::{Axis, Coordinate}};
+use field::{Field, helpers::Coordinate};
 use crate::heatmap;
-use crate::types::{ShotStatus, Printable};
-use crate::types::action::{Action, Argument::{Known, Unknown}};
+use crate::types::{IndexStyle, ShotStatus, Printable};
+use crate::types::field_ext::{Checkerboard, FieldExt};
+use crate::types::rules::Rules;
+use crate::types::ship_shape::ShipShape;
+use crate::types::action::{Action, Argument::{Known, Unknown}, Line};
 use anyhow::{anyhow, Ok, Result};
+use std::io::Write;
+use std::path::PathBuf;
+
+//Heat values are products of many fractions, so cells that should tie rarely agree to the last bit.
+//Anything this close to the best value counts as equally good.
+const TIE_TOLERANCE: f32 = 1e-6;
+
+//Every cell needs at most one shot, so automated play that takes longer than this is stuck, not unlucky.
+const TURN_LIMIT_SLACK: usize = 10;
+
+//Picks which of several possible placements a sunk ship occupied, by index.
+pub type LocationResolver = Box<dyn FnMut(&[Vec<Coordinate>]) -> usize>;
+
+pub enum ActionOutcome {
+    Continued,
+    //the last ship went down
+    GameOver,
+}
+
+//truth boards mark ships as hits, or as sunk
+fn is_truth_ship(status: &ShotStatus) -> bool {
+    status.is_hit() || status.is_sunk()
+}
+
+//rounded, and clamped so float error never pushes it past 100
+fn to_percentage(heat: f32) -> u8 {
+    (heat * 100.).round().clamp(0., 100.) as u8
+}
+
+//cool blue for cold cells, running up to hot red
+const COLD_COLOR: (u8, u8, u8) = (40, 90, 255);
+const HOT_COLOR: (u8, u8, u8) = (255, 60, 20);
+
+fn gradient_color(warmth: f32) -> (u8, u8, u8) {
+    let warmth = warmth.clamp(0., 1.);
+    let blend = |cold: u8, hot: u8| (cold as f32 + (hot as f32 - cold as f32) * warmth).round() as u8;
+    (
+        blend(COLD_COLOR.0, HOT_COLOR.0),
+        blend(COLD_COLOR.1, HOT_COLOR.1),
+        blend(COLD_COLOR.2, HOT_COLOR.2),
+    )
+}
+
+//the game as it was when the history started, so the history can be replayed on top of it
+struct HistoryOrigin {
+    shots: Field<ShotStatus>,
+    ships: Vec<usize>,
+    fleet: Vec<usize>,
+    sunk_ships: Vec<SunkShip>,
+}
+
+pub struct Stats {
+    pub shots_fired: usize,
+    pub hits: usize,
+    pub misses: usize,
+    pub untested: usize,
+    pub ships_sunk: usize,
+    pub ships_remaining: usize,
+}
+
+impl Stats {
+    pub fn hit_rate(&self) -> f32 {
+        if self.shots_fired == 0 {
+            return 0.;
+        }
+        self.hits as f32 / self.shots_fired as f32
+    }
+}
+
+//a ship that went down, and where
+#[derive(Clone)]
+pub struct SunkShip {
+    //index into the fleet the game started with, so ships of the same length stay apart
+    pub id: usize,
+    pub length: usize,
+    pub location: Vec<Coordinate>,
+    //what each cell of the location was before it sank, so unsinking can put it back
+    pub displaced_statuses: Vec<ShotStatus>,
+    //whether the hits allowed more than one location, so a plain 'sink' command couldn't tell where it went
+    pub ambiguous: bool,
+}
+
+impl SunkShip {
+    //cells that were still untested when it went down, which only a lenient sink allows
+    pub fn never_hit_cells(&self) -> usize {
+        self.displaced_statuses.iter().filter(|status| status.is_untested()).count()
+    }
+}
 
 pub struct State {
     shots: Field<ShotStatus>,
     ships: Vec<usize>,
+    //the fleet as it started out, plus any ships added by unsinking since
+    fleet: Vec<usize>,
+    sunk_ships: Vec<SunkShip>,
     heat_field: Field<f32>,
+    //the layers behind the heat field. None in the end game, when no heat is generated.
+    heat_breakdown: Option<heatmap::HeatBreakdown>,
+    //whatever looked inconsistent during the last update
+    diagnostics: Vec<heatmap::Diagnostic>,
     top_moves: Vec<Coordinate>,
     action_history: Vec<Action>,
+    //what each fire and hit in the history replaced, most recent last
+    displaced_statuses: Vec<ShotStatus>,
+    //the ship each sink in the history sent down, None for every other action. Lines up with the history.
+    history_sinks: Vec<Option<SunkShip>>,
+    //the actions after the current step, kept after going back in the history until something new is done
+    future_actions: Vec<(Action, Option<SunkShip>)>,
+    history_origin: HistoryOrigin,
+    rules: Rules,
+    auto_sink: bool,
+    location_resolver: LocationResolver,
+    heat_cache: heatmap::HeatCache,
+    //top moves get textual markers instead of colors
+    plain: bool,
+    //heat is shown as whole percentages instead of fractions
+    percent: bool,
+    //the real layout, when playing against a known board
+    truth: Option<Field<ShotStatus>>,
+    //untested cells are colored by their heat
+    gradient: bool,
+    //when set, picks the recommended move among tied top moves instead of taking the first
+    #[cfg(feature = "rand")]
+    tie_rng: Option<rand::rngs::StdRng>,
+    //overrides the turn limit of automated play
+    max_turns: Option<usize>,
+    //how likely the opponent is to put a ship on each cell, relative to the others. The heat gets multiplied by it.
+    prior: Option<Field<f32>>,
+    //every action taken gets appended to this file as a line of JSON
+    log: Option<PathBuf>,
 }
 impl State {
-    pub fn new(width: usize, height: usize, ships: &[usize]) -> Self {
+    pub fn new(width: usize, height: usize, ships: &[usize], rules: Rules) -> Result<Self> {
+        if width == 0 || height == 0 {
+            return Err(anyhow!("The board needs at least one row and one column."));
+        }
+        if ships.is_empty() {
+            return Err(anyhow!("The fleet needs at least one ship."));
+        }
+        if let Some(&ship_length) = ships
+            .iter()
+            .find(|&&ship_length| ship_length == 0 || ship_length > width.max(height))
+        {
+            return Err(anyhow!(
+                "A ship of length {ship_length} can't be placed on a {width}x{height} board."
+            ));
+        }
+
         let shots: Field<ShotStatus> = Field::new_default(width, height);
         let ships = ships.to_vec();
-        let heat_field = heatmap::gen_heat_field(&shots, &ships);
-        let top_moves= State::generate_top_moves(&heat_field);
+        let heat_field = heatmap::gen_heat_field(&shots, &ships, &rules);
+        let top_moves = vec![];
         let action_history = vec![];
+        let history_origin = HistoryOrigin {
+            shots: shots.clone(),
+            ships: ships.clone(),
+            fleet: ships.clone(),
+            sunk_ships: vec![],
+        };
 
-        State {
+        let mut state = State {
             shots,
+            fleet: ships.clone(),
             ships,
+            sunk_ships: vec![],
             heat_field,
+            heat_breakdown: None,
+            diagnostics: vec![],
             top_moves,
             action_history,
-        }
+            displaced_statuses: vec![],
+            history_sinks: vec![],
+            future_actions: vec![],
+            history_origin,
+            rules,
+            auto_sink: false,
+            location_resolver: State::interactive_resolver(rules.index_style),
+            heat_cache: heatmap::HeatCache::default(),
+            plain: false,
+            percent: false,
+            truth: None,
+            gradient: false,
+            #[cfg(feature = "rand")]
+            tie_rng: None,
+            max_turns: None,
+            prior: None,
+            log: None,
+        };
+        state.top_moves = state.generate_top_moves();
+        Ok(state)
+    }
+
+    //starts out from a board that's already partly played, with an empty history. The fleet is what's still afloat.
+    pub fn from_board(shots: Field<ShotStatus>, ships: &[usize], rules: Rules) -> Result<Self> {
+        let mut state = State::new(shots.width(), shots.height(), ships, rules)?;
+        state.set_shots(shots)?;
+        Ok(state)
     }
 
     fn update(&mut self) {
-        self.heat_field = heatmap::gen_heat_field(&self.shots, &self.ships);
-        self.top_moves = State::generate_top_moves(&self.heat_field);
+        if self.is_forced() {
+            //no need to count placements, every untested cell is a ship
+            self.heat_field = self.shots.transform_all(|status| if status.is_untested() { 1. } else { 0. });
+            self.heat_breakdown = None;
+            self.diagnostics.clear();
+        } else {
+            let (breakdown, diagnostics) = heatmap::gen_heat_field_detailed(
+                &self.shots,
+                &self.ships,
+                &self.rules,
+                &mut self.heat_cache,
+            );
+            self.heat_field = breakdown.combined.clone();
+            self.heat_breakdown = Some(breakdown);
+            self.diagnostics = diagnostics;
+            if let Some(prior) = &self.prior {
+                self.heat_field = self.heat_field.merge_field(prior, |heat, weight| heat * weight);
+            }
+        }
+        let hits = self.shots.count_where(ShotStatus::is_hit);
+        if hits > self.fleet_tonnage() {
+            self.diagnostics.push(heatmap::Diagnostic::TooManyHits { hits, tonnage: self.fleet_tonnage() });
+        }
+        self.top_moves = self.generate_top_moves();
+        #[cfg(feature = "rand")]
+        self.pick_tied_move();
+    }
+
+    //how many cells the ships still afloat take up together
+    pub fn fleet_tonnage(&self) -> usize {
+        self.ships.iter().sum()
+    }
+
+    pub fn remaining_untested_count(&self) -> usize {
+        self.shots.count_where(ShotStatus::is_untested)
+    }
+
+    //The end game: the ship cells not found yet fill up exactly the untested cells that are left.
+    //The hits so far belong to ships still afloat, as sunk ones are marked sunk.
+    fn is_forced(&self) -> bool {
+        let unfound = self.fleet_tonnage().saturating_sub(self.shots.count_where(ShotStatus::is_hit));
+        let untested = self.remaining_untested_count();
+        untested > 0 && untested == unfound
+    }
+
+    //throws away all cached placement counts and computes the heat from scratch
+    pub fn full_recompute(&mut self) {
+        self.heat_cache.clear();
+        self.update();
+    }
+
+    pub fn take_action(&mut self, action: Action) -> Result<ActionOutcome> {
+        let action = self.resolve_against_truth(action);
+        if let Action::Undo(count) = action {
+            let count = count.known_or(1);
+            self.undo(count)?;
+            return Ok(self.outcome());
+        }
+
+        let history_len = self.action_history.len();
+        let displaced = self.execute(action)?;
+        self.displaced_statuses.extend(displaced);
+        self.push_history(action);
+        self.future_actions.clear();
+
+        //a hit that completes a ship gets sunk right away, recorded as its own undoable action.
+        //The truth board knows the ship for certain, so it goes before guessing from the hits.
+        if let Action::Hit(Known(coord)) = action {
+            let truth_ship = self.truth.as_ref().and_then(|truth| self.completed_truth_ship(truth, coord));
+            if let Some(location) = truth_ship.filter(|location| self.ships.contains(&location.len())) {
+                let ship_length = location.len();
+                self.record_sunk(ship_length, location);
+                self.push_history(Action::Sink(Known(ship_length)));
+            } else if self.auto_sink {
+                if let Some((ship_length, location)) = self.find_completed_ship(coord) {
+                    self.record_sunk(ship_length, location);
+                    self.push_history(Action::Sink(Known(ship_length)));
+                }
+            }
+        }
+ 
+        self.update();
+
+        //any auto-sink included
+        for &logged_action in &self.action_history[history_len..] {
+            self.append_to_log(logged_action);
+        }
+
+        Ok(self.outcome())
     }
 
-    pub fn take_action(&mut self, mut action: Action) -> Result<()> {
+    //Undoes up to `count` actions, most recent first, and returns the ones that were undone.
+    //Stops early if the history runs out or an undo fails. The heat is only recomputed once at the end, from scratch.
+    pub fn undo(&mut self, count: usize) -> Result<Vec<Action>> {
+        if count == 0 {
+            return Err(anyhow!("Nothing to undo."));
+        }
 
-        //if the action is an "undo", convert it to the opposite of the last action
-        let mut action_was_undo= false;
-        if std::mem::discriminant(&action) == std::mem::discriminant(&Action::Undo){
-            action= self.get_last_action()?.opposite();
+        let mut undone = vec![];
+        while undone.len() < count {
+            let std::result::Result::Ok(last_action) = self.get_last_action() else {
+                break;
+            };
+            let result = match last_action {
+                Action::Fire(Known(coord)) | Action::Hit(Known(coord)) => {
+                    self.restore_displaced(coord)
+                }
+                //the statuses were saved in line order, so they come back off the end in reverse
+                Action::FireLine(Known(line)) => self.line_cells(line).and_then(|cells| {
+                    cells
+                        .into_iter()
+                        .rev()
+                        .try_for_each(|coord| self.restore_displaced(coord))
+                }),
+                _ => self.execute(last_action.opposite()).map(|_| ()),
+            };
+            if let Err(err) = result {
+                if undone.is_empty() {
+                    return Err(err);
+                }
+                break;
+            }
             self.action_history.pop();
-            action_was_undo= true;
+            self.history_sinks.pop();
+            undone.push(last_action);
         }
-        
-        //execute the action
+
+        if undone.is_empty() {
+            return Err(anyhow!("No more actions to undo."));
+        }
+
+        self.future_actions.clear();
+        self.full_recompute();
+        self.append_to_log(Action::Undo(Known(undone.len())));
+        Ok(undone)
+    }
+
+    //a sink goes down with wherever its ship ended up, so replaying it needn't ask again
+    fn push_history(&mut self, action: Action) {
+        let sunk_ship = match action {
+            Action::Sink(_) => self.sunk_ships.last().cloned(),
+            _ => None,
+        };
+        self.action_history.push(action);
+        self.history_sinks.push(sunk_ship);
+    }
+
+    //the current board becomes the one the history starts from
+    fn clear_history(&mut self) {
+        self.action_history.clear();
+        self.displaced_statuses.clear();
+        self.history_sinks.clear();
+        self.future_actions.clear();
+        self.history_origin = HistoryOrigin {
+            shots: self.shots.clone(),
+            ships: self.ships.clone(),
+            fleet: self.fleet.clone(),
+            sunk_ships: self.sunk_ships.clone(),
+        };
+    }
+
+    //how many actions there are to go back and forth between, including any ahead of the current step
+    pub fn timeline_len(&self) -> usize {
+        self.action_history.len() + self.future_actions.len()
+    }
+
+    //Rebuilds the board from where the history started, with only the first `step` actions taken.
+    //The actions after it are kept, so going forward again works too, until a new action is taken.
+    //Should an action fail along the way, the game is left as it was, timeline and all.
+    pub fn replay_to(&mut self, step: usize) -> Result<()> {
+        if step > self.timeline_len() {
+            return Err(anyhow!("There are only {} actions to go to.", self.timeline_len()));
+        }
+
+        let board = HistoryOrigin {
+            shots: self.shots.clone(),
+            ships: self.ships.clone(),
+            fleet: self.fleet.clone(),
+            sunk_ships: self.sunk_ships.clone(),
+        };
+        let displaced_statuses = self.displaced_statuses.clone();
+        let action_history = self.action_history.clone();
+        let history_sinks = self.history_sinks.clone();
+        let future_actions = self.future_actions.clone();
+
+        if let Err(err) = self.replay_timeline(step) {
+            self.shots = board.shots;
+            self.ships = board.ships;
+            self.fleet = board.fleet;
+            self.sunk_ships = board.sunk_ships;
+            self.displaced_statuses = displaced_statuses;
+            self.action_history = action_history;
+            self.history_sinks = history_sinks;
+            self.future_actions = future_actions;
+            return Err(err);
+        }
+
+        self.full_recompute();
+        Ok(())
+    }
+
+    //the actual replay for replay_to, which puts everything back if this fails halfway
+    fn replay_timeline(&mut self, step: usize) -> Result<()> {
+        let mut timeline = self
+            .action_history
+            .drain(..)
+            .zip(self.history_sinks.drain(..))
+            .collect::<Vec<_>>();
+        timeline.append(&mut self.future_actions);
+
+        self.shots = self.history_origin.shots.clone();
+        self.ships = self.history_origin.ships.clone();
+        self.fleet = self.history_origin.fleet.clone();
+        self.sunk_ships = self.history_origin.sunk_ships.clone();
+        self.displaced_statuses.clear();
+
+        let future_actions = timeline.split_off(step);
+        for (action, sunk_ship) in timeline {
+            match (action, sunk_ship) {
+                (Action::Sink(Known(ship_length)), Some(ship)) => {
+                    self.record_sunk(ship_length, ship.location)
+                }
+                (action, _) => {
+                    let displaced = self.execute(action)?;
+                    self.displaced_statuses.extend(displaced);
+                }
+            }
+            self.push_history(action);
+        }
+        self.future_actions = future_actions;
+        Ok(())
+    }
+
+    pub fn undo_all(&mut self) -> Result<()> {
+        self.replay_to(0)
+    }
+
+    pub fn set_log(&mut self, log: Option<PathBuf>) {
+        self.log = log;
+    }
+
+    //A log that can't be written to is no reason to stop the game, so it only warns.
+    fn append_to_log(&self, action: Action) {
+        let Some(path) = &self.log else {
+            return;
+        };
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        let line = serde_json::json!({
+            "time": time,
+            "action": action.name(),
+            "command": action.tx_command(self.rules.index_style),
+            "top_move": self.recommend().as_ref().map(|coord| coord.to_user(self.rules.index_style)),
+        });
+
+        let result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{line}"));
+        if let Err(err) = result {
+            println!("!!WARNING!!\nUnable to write to the log '{}': {err}", path.display());
+        }
+    }
+
+    //returns the statuses a fire, hit or fireline replaced, in order, so undoing it can put them back exactly
+    fn execute(&mut self, action: Action) -> Result<Vec<ShotStatus>> {
+        let mut displaced = vec![];
         match action {
-            Action::Fire(Known(coord)) => self.shots.set_value(coord, &ShotStatus::Miss)?,
-            Action::Unfire(Known(coord)) => self.shots.set_value(coord, &ShotStatus::Untested)?,
-            Action::Hit(Known(coord)) => self.shots.set_value(coord, &ShotStatus::Hit)?,
+            Action::Fire(Known(coord)) => {
+                displaced.push(self.shots.replace_value(coord, ShotStatus::Miss)?)
+            }
+            Action::Unfire(Known(coord)) => self.unfire(coord)?,
+            Action::Unhit(Known(coord)) => self.unhit(coord)?,
+            Action::Hit(Known(coord)) => {
+                displaced.push(self.shots.replace_value(coord, ShotStatus::Hit)?)
+            }
             Action::Sink(Known(ship_length)) => self.sink_ship(ship_length)?,
-            Action::Unsink(Known(ship_length)) => self.ships.push(ship_length),
+            Action::Unsink(Known(ship_length)) => self.unsink_ship(ship_length)?,
+            Action::FireLine(Known(line)) => displaced = self.fire_line(line)?,
+            Action::UnfireLine(Known(line)) => self.unfire_line(line)?,
 
             Action::Fire(Unknown)
-            | Action::Unfire(Unknown) | Action::Hit(Unknown)
+            | Action::Unfire(Unknown) | Action::Hit(Unknown) | Action::Unhit(Unknown)
             | Action::Sink(Unknown)
-            | Action::Unsink(Unknown) => {
+            | Action::Unsink(Unknown)
+            | Action::FireLine(Unknown) | Action::UnfireLine(Unknown) => {
                 unreachable!("Actions with unknown arguments cannot be taken.")
             }
 
-            Action::Undo => unreachable!("Undos were already converted to the appropriate action."),
+            Action::Undo(_) => unreachable!("Undos are converted to the opposite of the undone action."),
         };
 
-        if !action_was_undo {
-            self.action_history.push(action);
+        Ok(displaced)
+    }
+
+    //only a miss is taken back this way. Hits and sunk cells have to be undone, which knows what was there before.
+    fn unfire(&mut self, coord: Coordinate) -> Result<()> {
+        match self.status_at(coord) {
+            Some(ShotStatus::Miss) => self.shots.set_value(coord, &ShotStatus::Untested),
+            Some(status) => Err(anyhow!(
+                "{} is marked as '{}', only misses can be unfired. Use 'undo' instead.",
+                coord.printable(self.rules.index_style),
+                status.name()
+            )),
+            None => Err(anyhow!("{} is not on the board.", coord.printable(self.rules.index_style))),
         }
- 
-        self.update();
+    }
 
+    //every untested cell of the line becomes a miss. Returns what each cell of the line was before, changed or not.
+    fn fire_line(&mut self, line: Line) -> Result<Vec<ShotStatus>> {
+        let mut displaced = vec![];
+        for coord in self.line_cells(line)? {
+            let status = self.shots.get_grid()[coord.row][coord.column];
+            if status.is_untested() {
+                self.shots.set_value(coord, &ShotStatus::Miss)?;
+            }
+            displaced.push(status);
+        }
+        Ok(displaced)
+    }
 
+    //like unfire, for every miss of the line at once
+    fn unfire_line(&mut self, line: Line) -> Result<()> {
+        for coord in self.line_cells(line)? {
+            if self.shots.get_grid()[coord.row][coord.column].is_miss() {
+                self.shots.set_value(coord, &ShotStatus::Untested)?;
+            }
+        }
         Ok(())
     }
 
-    fn sink_ship(&mut self, ship_length: usize) -> Result<()> {
-        let position = self
-            .ships
-            .iter()
-            .position(|&ship| ship == ship_length)
-            .ok_or_else(|| anyhow::anyhow!("Ship not found."))?;
+    fn line_cells(&self, line: Line) -> Result<Vec<Coordinate>> {
+        if line.index >= self.shots.number_of_lines_in_axis(line.axis) {
+            return Err(anyhow!("{} is not on the board.", line.printable(self.rules.index_style)));
+        }
+        let mut start = Coordinate::default();
+        start.set_axis_index(line.axis, line.index);
+        let length = self.shots.number_of_lines_in_axis(line.axis.opposite());
+        Ok(heatmap::placement_cells(start, line.axis.opposite(), length))
+    }
 
-        let ship_locations= self.generate_possible_ship_locations(ship_length);
-        if ship_locations.is_empty() {return Err(anyhow::anyhow!("Ship doesn't fit existing hits."));}
+    //a hit is only ever recorded on a cell that was shot at, so it goes back to being a miss
+    fn unhit(&mut self, coord: Coordinate) -> Result<()> {
+        match self.status_at(coord) {
+            Some(ShotStatus::Hit) => self.shots.set_value(coord, &ShotStatus::Miss),
+            Some(status) => Err(anyhow!(
+                "{} is marked as '{}', only hits can be unhit.",
+                coord.printable(self.rules.index_style),
+                status.name()
+            )),
+            None => Err(anyhow!("{} is not on the board.", coord.printable(self.rules.index_style))),
+        }
+    }
 
-        self.ships.remove(position);
+    fn restore_displaced(&mut self, coord: Coordinate) -> Result<()> {
+        let status = self
+            .displaced_statuses
+            .last()
+            .copied()
+            .ok_or_else(|| {
+                anyhow!("Nothing is known about {} before it was shot.", coord.printable(self.rules.index_style))
+            })?;
+        self.shots.set_value(coord, &status)?;
+        self.displaced_statuses.pop();
+        Ok(())
+    }
 
-        if self.ships.is_empty() {
-            return Err(anyhow::anyhow!("Game is over, go home :)"));
+    fn outcome(&self) -> ActionOutcome {
+        if self.is_complete() {
+            ActionOutcome::GameOver
+        } else {
+            ActionOutcome::Continued
         }
+    }
 
-        
+    //undoing the final sink puts the ship back, which un-completes the game again
+    pub fn is_complete(&self) -> bool {
+        self.ships.is_empty()
+    }
+
+    //Plays the game to the end against a known layout, always taking the top move, and returns the number of shots fired.
+    //Ship cells are the hit or sunk cells of the truth board, and ships there must not touch, otherwise they can't be told apart.
+    pub fn autoplay(&mut self, truth: &Field<ShotStatus>) -> Result<usize> {
+        if truth.width() != self.shots.width() || truth.height() != self.shots.height() {
+            return Err(anyhow!(
+                "Expected a {}x{} board, got {}x{}.",
+                self.shots.width(),
+                self.shots.height(),
+                truth.width(),
+                truth.height()
+            ));
+        }
+        let mut shots_fired = 0;
+        while !self.is_complete() {
+            if shots_fired >= self.turn_limit() {
+                return Err(self.turn_limit_error());
+            }
+            let coord = self
+                .recommend()
+                .ok_or_else(|| anyhow!("Ran out of moves with ships left afloat."))?;
+            shots_fired += 1;
+
+            if !is_truth_ship(&truth.get_grid()[coord.row][coord.column]) {
+                self.take_action(Action::Fire(Known(coord)))?;
+                continue;
+            }
+            self.take_action(Action::Hit(Known(coord)))?;
+
+            //auto-sink may already have taken care of it
+            if let Some(ship) = self.completed_truth_ship(truth, coord) {
+                let ship_length = ship.len();
+                let previous = self.replace_location_resolver(move |locations| {
+                    locations
+                        .iter()
+                        .position(|location| {
+                            location.len() == ship.len()
+                                && location.iter().all(|cell| ship.contains(cell))
+                        })
+                        .unwrap_or(usize::MAX)
+                });
+                let sunk = self.take_action(Action::Sink(Known(ship_length)));
+                self.restore_location_resolver(previous);
+                sunk?;
+            }
+        }
+
+        Ok(shots_fired)
+    }
+
+    //the ship of the truth board through the coordinate, if every one of its cells is hit by now
+    fn completed_truth_ship(&self, truth: &Field<ShotStatus>, coord: Coordinate) -> Option<Vec<Coordinate>> {
+        truth
+            .connected_components(is_truth_ship, false)
+            .into_iter()
+            .find(|ship| ship.contains(&coord))
+            .filter(|ship| ship.iter().all(|&cell| self.status_at(cell).is_some_and(|status| status.is_hit())))
+    }
+
+    //From now on, fires get checked against the given board, and turn into hits where it has a ship.
+    //Ships it shows as fully hit get sunk right away.
+    pub fn set_truth(&mut self, truth: Field<ShotStatus>) -> Result<()> {
+        if truth.width() != self.shots.width() || truth.height() != self.shots.height() {
+            return Err(anyhow!(
+                "Expected a {}x{} board, got {}x{}.",
+                self.shots.width(),
+                self.shots.height(),
+                truth.width(),
+                truth.height()
+            ));
+        }
+        self.truth = Some(truth);
+        Ok(())
+    }
+
+    //weights the heat by how the opponent tends to place ships, e.g. higher along the edges for one that hugs them
+    pub fn set_prior(&mut self, prior: Field<f32>) -> Result<()> {
+        if prior.width() != self.shots.width() || prior.height() != self.shots.height() {
+            return Err(anyhow!(
+                "Expected a {}x{} prior, got {}x{}.",
+                self.shots.width(),
+                self.shots.height(),
+                prior.width(),
+                prior.height()
+            ));
+        }
+        if prior.count_where(|weight| weight.is_nan() || *weight < 0.) > 0 {
+            return Err(anyhow!("The weights of a prior can't be negative."));
+        }
+        self.prior = Some(prior);
+        self.update();
+        Ok(())
+    }
+
+    //a fire at a ship of the truth board is really a hit. Everything else is left as is.
+    pub fn resolve_against_truth(&self, action: Action) -> Action {
+        match (action, &self.truth) {
+            (Action::Fire(Known(coord)), Some(truth))
+                if truth.get_grid().get(coord.row).and_then(|line| line.get(coord.column)).is_some_and(is_truth_ship) =>
+            {
+                Action::Hit(Known(coord))
+            }
+            _ => action,
+        }
+    }
+
+    pub fn set_auto_sink(&mut self, auto_sink: bool) {
+        self.auto_sink = auto_sink;
+    }
+
+    //ties get broken pseudo-randomly from now on, the same way for the same seed
+    #[cfg(feature = "rand")]
+    pub fn set_seed(&mut self, seed: u64) {
+        use rand::SeedableRng;
+
+        self.tie_rng = Some(rand::rngs::StdRng::seed_from_u64(seed));
+        self.pick_tied_move();
+    }
+
+    //moves a random top move to the front. The center tie-break already decides ties, so it's left alone.
+    #[cfg(feature = "rand")]
+    fn pick_tied_move(&mut self) {
+        use rand::Rng;
+
+        let Some(rng) = &mut self.tie_rng else {
+            return;
+        };
+        if self.top_moves.len() > 1 && !self.rules.center_tie_break {
+            let pick = rng.gen_range(0..self.top_moves.len());
+            self.top_moves.swap(0, pick);
+        }
+    }
+
+    pub fn set_plain(&mut self, plain: bool) {
+        self.plain = plain;
+    }
+
+    pub fn set_percent(&mut self, percent: bool) {
+        self.percent = percent;
+    }
+
+    pub fn set_gradient(&mut self, gradient: bool) {
+        self.gradient = gradient;
+    }
+
+    pub fn set_max_turns(&mut self, max_turns: Option<usize>) {
+        self.max_turns = max_turns;
+    }
+
+    //how many shots automated play may take before it gives up, a safety net against looping on resolved cells
+    pub fn turn_limit(&self) -> usize {
+        self.max_turns.unwrap_or(self.shots.width() * self.shots.height() + TURN_LIMIT_SLACK)
+    }
+
+    pub fn turn_limit_error(&self) -> anyhow::Error {
+        anyhow!("Gave up after {} turns with ships left afloat.", self.turn_limit())
+    }
+
+    //replaces the interactive prompt, so sinking can be driven by scripts
+    pub fn set_location_resolver(
+        &mut self,
+        resolver: impl FnMut(&[Vec<Coordinate>]) -> usize + 'static,
+    ) {
+        self.location_resolver = Box::new(resolver);
+    }
+
+    //like set_location_resolver, but hands back the resolver it replaced so it can be restored afterwards
+    pub fn replace_location_resolver(
+        &mut self,
+        resolver: impl FnMut(&[Vec<Coordinate>]) -> usize + 'static,
+    ) -> LocationResolver {
+        std::mem::replace(&mut self.location_resolver, Box::new(resolver))
+    }
+
+    //puts back a resolver handed out by replace_location_resolver
+    pub fn restore_location_resolver(&mut self, resolver: LocationResolver) {
+        self.location_resolver = resolver;
+    }
+
+    pub fn reset_location_resolver(&mut self) {
+        self.location_resolver = State::interactive_resolver(self.rules.index_style);
+    }
+
+    fn find_completed_ship(&self, coord: Coordinate) -> Option<(usize, Vec<Coordinate>)> {
+        let mut ship_lengths = self.ships.clone();
+        ship_lengths.sort_unstable();
+        ship_lengths.dedup();
 
+        ship_lengths.into_iter().find_map(|ship_length| {
+            self.generate_possible_ship_locations(ShipShape::line(ship_length), false)
+                .into_iter()
+                .find(|location| location.contains(&coord) && self.is_bounded(location))
+                .map(|location| (ship_length, location))
+        })
+    }
 
-        let chosen_location= if ship_locations.len() == 1 {ship_locations.first().unwrap().clone()} else {
-            Self::ask_user_for_ship_location(ship_locations)
+    //a run of hits is bounded if nothing could possibly continue it on either end
+    fn is_bounded(&self, location: &[Coordinate]) -> bool {
+        let (first, last) = (location[0], location[location.len() - 1]);
+        let is_blocked = |coord: Coordinate, (row_step, column_step): (isize, isize)| {
+            match (coord.row.checked_add_signed(row_step), coord.column.checked_add_signed(column_step)) {
+                (Some(row), Some(column)) => self
+                    .status_at(Coordinate { row, column })
+                    .is_none_or(|status| !status.can_contain_ship()),
+                _ => true,
+            }
+        };
+        let bounded_along = |(row_step, column_step): (isize, isize)| {
+            is_blocked(first, (-row_step, -column_step)) && is_blocked(last, (row_step, column_step))
         };
 
-        for coord in chosen_location {
-            self.shots.set_value(coord, &ShotStatus::Sunk).unwrap();
+        //a single cell lies on every axis, so it has to be closed off on all of them
+        if location.len() == 1 {
+            let mut directions = vec![(0, 1), (1, 0)];
+            if self.rules.allow_diagonal {
+                directions.extend([(1, 1), (-1, 1)]);
+            }
+            directions.into_iter().all(bounded_along)
+        } else {
+            let second = location[1];
+            bounded_along((
+                second.row as isize - first.row as isize,
+                second.column as isize - first.column as isize,
+            ))
         }
+    }
 
-      
+    //takes the ship off the remaining list, marks its cells and remembers which ship of the fleet it was
+    fn record_sunk(&mut self, ship_length: usize, location: Vec<Coordinate>) {
+        let ambiguous = self
+            .generate_possible_ship_locations(ShipShape::line(ship_length), self.rules.lenient_sink)
+            .len()
+            > 1;
+        let position = self.ships.iter().position(|&ship| ship == ship_length).unwrap();
+        self.ships.remove(position);
+        let displaced_statuses = location
+            .iter()
+            .map(|&coord| self.shots.replace_value(coord, ShotStatus::Sunk).unwrap())
+            .collect();
+
+        let id = (0..self.fleet.len())
+            .find(|&id| {
+                self.fleet[id] == ship_length && self.sunk_ships.iter().all(|ship| ship.id != id)
+            })
+            .unwrap_or_else(|| {
+                self.fleet.push(ship_length);
+                self.fleet.len() - 1
+            });
+        self.sunk_ships.push(SunkShip {
+            id,
+            length: ship_length,
+            location,
+            displaced_statuses,
+            ambiguous,
+        });
+    }
+
+    pub fn sunk_ships(&self) -> &[SunkShip] {
+        &self.sunk_ships
+    }
+
+    fn sink_ship(&mut self, ship_length: usize) -> Result<()> {
+        if !self.ships.contains(&ship_length) {
+            return Err(anyhow::anyhow!("Ship not found."));
+        }
+
+        let ship_locations= self.generate_possible_ship_locations(ShipShape::line(ship_length), self.rules.lenient_sink);
+        if ship_locations.is_empty() {return Err(anyhow::anyhow!("Ship doesn't fit existing hits."));}
+
+        let chosen_location = if ship_locations.len() == 1 {
+            ship_locations.first().unwrap().clone()
+        } else {
+            let idx = (self.location_resolver)(&ship_locations);
+            ship_locations
+                .get(idx)
+                .ok_or_else(|| anyhow::anyhow!("Chosen ship location doesn't exist."))?
+                .clone()
+        };
+
+        self.record_sunk(ship_length, chosen_location);
 
+        Ok(())
+    }
 
+    //every ship that was sunk came from a valid fleet, so undoing a sink always passes this
+    fn unsink_ship(&mut self, ship_length: usize) -> Result<()> {
+        let (width, height) = (self.shots.width(), self.shots.height());
+        if ship_length == 0 || ship_length > width.max(height) {
+            return Err(anyhow!(
+                "A ship of length {ship_length} can't be placed on a {width}x{height} board."
+            ));
+        }
 
+        //the most recently sunk ship of that length floats again, if there is one, and its cells go back to how they were
+        if let Some(idx) = self.sunk_ships.iter().rposition(|ship| ship.length == ship_length) {
+            let ship = self.sunk_ships.remove(idx);
+            for (&coord, status) in ship.location.iter().zip(&ship.displaced_statuses) {
+                self.shots.set_value(coord, status)?;
+            }
+        }
+        self.ships.push(ship_length);
         Ok(())
     }
 
-    fn generate_possible_ship_locations(&self, ship_length: usize) -> Vec<Vec<Coordinate>>{
-        [Axis::Row, Axis::Column].iter().flat_map(|&axis|{
-            (0..self.shots.number_of_lines_in_axis(axis)).flat_map(|index|{
-                self.shots.get_line(axis, index).unwrap().windows(ship_length).enumerate().filter(|(_,window)|{
-                    window.iter().all(|status| std::mem::discriminant(status) == std::mem::discriminant(&ShotStatus::Hit))
-                }).map(|(off_axis_idx, _)|{
-                    (0..ship_length).map(move|offset|{
-                        let mut coord= Coordinate::default();
-                        coord.set_axis_index(axis, index);
-                        coord.set_axis_index(axis.opposite(), off_axis_idx+offset);
-                        coord
-                    }).collect::<Vec<_>>()
-                }).collect::<Vec<_>>()
-            }).collect::<Vec<_>>()
-        }).collect()
+    //normally only runs of hits qualify. With allow_untested, any run of hits and untested cells with at least one hit does.
+    fn generate_possible_ship_locations(
+        &self,
+        shape: ShipShape,
+        allow_untested: bool,
+    ) -> Vec<Vec<Coordinate>> {
+        //no two ships share a cell, whatever the board says
+        let is_taken = |coord: &Coordinate| {
+            self.sunk_ships
+                .iter()
+                .any(|ship| ship.location.contains(coord))
+        };
+        let fits = |location: &Vec<Coordinate>| {
+            let mut cells = location.iter().filter_map(|&coord| self.status_at(coord));
+            if allow_untested {
+                cells.clone().all(|status| status.is_hit() || status.is_untested())
+                    && cells.any(|status| status.is_hit())
+            } else {
+                cells.all(|status| status.is_hit())
+            }
+        };
+
+        //horizontal first, then vertical
+        let mut locations = shape
+            .orientations()
+            .into_iter()
+            .flat_map(|(width, height)| {
+                self.shots
+                    .windows_2d(width, height)
+                    .map(move |(corner, _)| heatmap::footprint_cells(corner, width, height))
+            })
+            .collect::<Vec<_>>();
+        //then along the diagonals, where the rules allow lines to lie that way
+        if self.rules.allow_diagonal && shape.is_line() && shape.cell_count() > 1 {
+            locations.extend(heatmap::diagonal_footprints(
+                self.shots.width(),
+                self.shots.height(),
+                shape.cell_count(),
+            ));
+        }
+
+        locations
+            .into_iter()
+            .filter(|location| fits(location) && !location.iter().any(is_taken))
+            .collect()
+    }
+
+    //the prompt shows the locations the way the rest of the game does
+    fn interactive_resolver(style: IndexStyle) -> LocationResolver {
+        Box::new(move |ship_locations| State::ask_user_for_ship_location(ship_locations, style))
     }
 
-    fn ask_user_for_ship_location(ship_locations: Vec<Vec<Coordinate>>) -> Vec<Coordinate>{
-        use std::io::Write;
+    fn ask_user_for_ship_location(ship_locations: &[Vec<Coordinate>], style: IndexStyle) -> usize {
+        State::read_ship_location(ship_locations, style, &mut std::io::stdin().lock())
+    }
+
+    //the prompt itself, reading the answers from wherever they come from
+    fn read_ship_location(ship_locations: &[Vec<Coordinate>], style: IndexStyle, answers: &mut impl std::io::BufRead) -> usize {
 
         println!("The ship to sink could be in multiple places. Please select one:");
         for (idx, location) in ship_locations.iter().enumerate(){
             print!("{}: ",idx+1);
             for coord in location {
-                print!("{}", coord.printable())
+                print!("{}", coord.printable(style))
             }
             println!()
         }
@@ -137,10 +962,14 @@ impl State {
         loop{
             std::io::stdout().flush().unwrap();
             let mut input = String::new();
-            std::io::stdin().read_line(&mut input).unwrap();
+            //with stdin closed there is nobody left to ask. An index past the end makes the sink fail instead of hang.
+            if !matches!(answers.read_line(&mut input), std::result::Result::Ok(read) if read > 0) {
+                println!("No input left to pick a location from.");
+                return usize::MAX;
+            }
             if let std::result::Result::Ok(idx) = input.trim().parse::<usize>() {
-                if let Some(location) = ship_locations.get(idx.saturating_sub(1)){
-                    return location.clone();
+                if (1..=ship_locations.len()).contains(&idx) {
+                    return idx - 1;
                 }
             }
             println!("Invalid, please try again.");
@@ -150,69 +979,1275 @@ impl State {
       
     }
 
-    pub fn get_top_moves(&self) -> Vec<Coordinate>{
-        self.top_moves.clone()
+    pub fn base_heat(&self) -> Option<&Field<f32>> {
+        self.heat_breakdown.as_ref().map(|breakdown| &breakdown.base)
     }
 
-    fn generate_top_moves(heat_field: &Field<f32>) -> Vec<Coordinate> {
-        let mut max_val= f32::NEG_INFINITY;
-        let mut top_moves= vec![];
-        heat_field.get_grid().iter().enumerate().flat_map(|(row_idx, row)|{
-            row.iter().enumerate().map(move |(column_idx, val)| (Coordinate{row: row_idx, column: column_idx}, val))
-        }).for_each(|(coord, &val)|{
-            if val > max_val {
-                max_val= val;
-                top_moves.clear();
-                top_moves.push(coord);
-            } else if (val - max_val).abs() <= f32::EPSILON {
-                top_moves.push(coord);
-            }
-        }); 
-        
-        top_moves
+    pub fn hit_heat(&self) -> Option<&Field<f32>> {
+        self.heat_breakdown.as_ref().map(|breakdown| &breakdown.hit)
+    }
 
+    pub fn cluster_heat(&self) -> Option<&Field<f32>> {
+        self.heat_breakdown.as_ref().map(|breakdown| &breakdown.cluster)
     }
 
-    pub fn get_last_action(&mut self) -> Result<Action> {
-        self.action_history.last().ok_or_else(|| anyhow!("No more actions to undo.")).copied()
+    //expects the coordinate to be on the board
+    pub fn explain(&self, coord: Coordinate) -> heatmap::CellExplanation {
+        heatmap::explain_cell(&self.shots, &self.ships, &self.rules, coord)
+    }
 
+    //the remaining ship lengths that could still cover the cell, shortest first
+    pub fn possible_ship_lengths_at(&self, coord: Coordinate) -> Vec<usize> {
+        let mut ship_lengths = self
+            .explain(coord)
+            .placements
+            .into_iter()
+            .filter(|&(_, placement_count)| placement_count > 0)
+            .map(|(ship_length, _)| ship_length)
+            .collect::<Vec<_>>();
+        //explain goes longest first
+        ship_lengths.reverse();
+        ship_lengths
     }
 
-    pub fn get_last_matching_action(&self, action: Action) -> Result<Action> {
-        match action {
-            Action::Undo => unreachable!("Undo-s or actions without associated data may never be appended to the action history."),
-            Action::Fire(_) | Action::Unfire(_) | Action::Hit(_) | Action::Sink(_) | Action::Unsink(_) => {
-                self.action_history.iter().rev().find(|&&act| std::mem::discriminant(&act)==std::mem::discriminant(&action)).ok_or_else(|| anyhow::anyhow!("Could not find last instance of action in history.")).copied()
-            }
-            
+    //how many placements of a remaining ship cover each cell, and how many there are in total
+    pub fn placements(&self, ship_length: usize) -> Result<(Field<usize>, usize)> {
+        if !self.ships.contains(&ship_length) {
+            return Err(anyhow!("There is no ship of length {ship_length} left."));
         }
+        Ok(heatmap::placement_field(&self.shots, ship_length, &self.rules))
     }
 
-    pub fn debug_print_state(&self) {
-        use colored::Colorize;
-        println!("Board State:");
-        for (row_idx, (heat_line, status_line)) in self.heat_field.get_grid().iter().zip(self.shots.get_grid().iter()).enumerate() {
-            for (column_idx,(heat, status)) in heat_line.iter().zip(status_line.iter()).enumerate() {
-                match status {
-                    ShotStatus::Untested => {
-                        let coord= Coordinate{row: row_idx, column: column_idx};
-                        if let Some((idx,_))= self.top_moves.iter().enumerate().find(|(idx,&top_move_coord)|coord == top_move_coord){
-                            if idx == 0{
-                                print!("{}",format!("[{:.2}]", heat).red())
-                            } else {
-                                print!("{}",format!("[{:.2}]", heat).green())
-                            }
-                        } else {
-                            print!("[{:.2}]", heat)
-                        }
-                    },
-                    ShotStatus::Hit => print!("[####]"),
-                    ShotStatus::Miss => print!("[----]"),
-                    ShotStatus::Sunk => print!("[||||]"),
-                }
+    pub fn probability_at(&self, coord: Coordinate) -> Option<f32> {
+        self.heat_field.get_grid().get(coord.row)?.get(coord.column).copied()
+    }
+
+    //the heat as whole percentages, resolved cells are 0
+    pub fn probability_percentages(&self) -> Field<u8> {
+        self.heat_field.merge_field(&self.shots, |&heat, status| {
+            if status.is_untested() {
+                to_percentage(heat)
+            } else {
+                0
             }
-            println!()
-        }
-       
+        })
+    }
+
+    //true for every untested cell whose heat reaches the threshold. Resolved cells never count.
+    pub fn likely_mask(&self, threshold: f32) -> Field<bool> {
+        self.heat_field
+            .merge_field(&self.shots, |&heat, status| status.is_untested() && heat >= threshold)
+    }
+
+    //the heat as it would be if the shot at the coordinate turned out as given. Nothing about the game changes.
+    //The coordinate has to be on the board.
+    pub fn simulate(&self, coord: Coordinate, outcome: ShotStatus) -> Field<f32> {
+        heatmap::gen_heat_field(&self.simulated_shots(coord, outcome), &self.ships, &self.rules)
+    }
+
+    //the board as debug_print_state would show it after the simulated shot, without any top moves marked
+    pub fn simulated_board_ascii(&self, coord: Coordinate, outcome: ShotStatus) -> String {
+        let shots = self.simulated_shots(coord, outcome);
+        let heat_field = self.simulate(coord, outcome);
+        self.format_board(&shots, &heat_field, &[], true)
+    }
+
+    fn simulated_shots(&self, coord: Coordinate, outcome: ShotStatus) -> Field<ShotStatus> {
+        let mut shots = self.shots.clone();
+        shots.set_value(coord, &outcome).unwrap();
+        shots
+    }
+
+    pub fn get_heat_field(&self) -> &Field<f32> {
+        &self.heat_field
+    }
+
+    //derived from the board and the roster, so undos and replaced boards never need bookkeeping
+    pub fn stats(&self) -> Stats {
+        let status_counts = self.shots.count_by(|&status| status);
+        let count_of = |status: ShotStatus| status_counts.get(&status).copied().unwrap_or(0);
+        let hits = count_of(ShotStatus::Hit) + count_of(ShotStatus::Sunk);
+        let misses = count_of(ShotStatus::Miss);
+
+        Stats {
+            shots_fired: hits + misses,
+            hits,
+            misses,
+            untested: count_of(ShotStatus::Untested),
+            ships_sunk: self.sunk_ships.len(),
+            ships_remaining: self.ships.len(),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let board = self
+            .shots
+            .get_grid()
+            .iter()
+            .map(|line| line.iter().map(ShotStatus::name).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let top_moves = self
+            .top_moves
+            .iter()
+            .map(|coord| coord.to_user(self.rules.index_style))
+            .collect::<Vec<_>>();
+
+        serde_json::json!({
+            "board": board,
+            "heat": self.heat_field.get_grid(),
+            "top_moves": top_moves,
+            "ships": self.ships,
+            "complete": self.is_complete(),
+            "warnings": self
+                .diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.tx_message(self.rules.index_style))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    //grows or shrinks the board on the right and bottom edges. Shrinking may only drop untested cells and misses.
+    //Like replacing the board, this clears the history.
+    pub fn resize_board(&mut self, width: usize, height: usize) -> Result<()> {
+        if width == 0 || height == 0 {
+            return Err(anyhow!("The board needs at least one row and one column."));
+        }
+        if let Some(&ship_length) = self
+            .ships
+            .iter()
+            .find(|&&ship_length| ship_length > width.max(height))
+        {
+            return Err(anyhow!(
+                "A ship of length {ship_length} can't be placed on a {width}x{height} board."
+            ));
+        }
+        let dropped_ship_cell = self.shots.iter_coords().find(|(coord, status)| {
+            (coord.row >= height || coord.column >= width) && (status.is_hit() || status.is_sunk())
+        });
+        if let Some((coord, status)) = dropped_ship_cell {
+            return Err(anyhow!(
+                "{} is marked as '{}' and would be cut off.",
+                coord.printable(self.rules.index_style),
+                status.name()
+            ));
+        }
+
+        self.shots.resize(width, height, ShotStatus::Untested);
+        //new cells get no bias either way
+        if let Some(prior) = &mut self.prior {
+            prior.resize(width, height, 1.);
+        }
+        self.clear_history();
+        self.full_recompute();
+        Ok(())
+    }
+
+    pub fn remaining_ships(&self) -> &[usize] {
+        &self.ships
+    }
+
+    pub fn get_shots(&self) -> &Field<ShotStatus> {
+        &self.shots
+    }
+
+    //replaces the whole board. The history no longer matches it afterwards, so it's cleared and can't be undone.
+    pub fn set_shots(&mut self, shots: Field<ShotStatus>) -> Result<()> {
+        if shots.width() != self.shots.width() || shots.height() != self.shots.height() {
+            return Err(anyhow!(
+                "Expected a {}x{} board, got {}x{}.",
+                self.shots.width(),
+                self.shots.height(),
+                shots.width(),
+                shots.height()
+            ));
+        }
+
+        self.shots = shots;
+        self.sunk_ships.clear();
+        self.clear_history();
+        self.full_recompute();
+        Ok(())
+    }
+
+    pub fn status_at(&self, coord: Coordinate) -> Option<ShotStatus> {
+        self.shots.get_grid().get(coord.row)?.get(coord.column).copied()
+    }
+
+    pub fn get_top_moves(&self) -> Vec<Coordinate>{
+        self.top_moves.clone()
+    }
+
+    //the single best move, or None once every cell is resolved. Top moves are only ever untested cells.
+    pub fn recommend(&self) -> Option<Coordinate> {
+        self.top_moves.first().copied()
+    }
+
+    fn generate_top_moves(&self) -> Vec<Coordinate> {
+        let ranked_moves = if self.rules.parity_hunt {
+            self.hunt_parity_moves()
+        } else {
+            self.ranked_moves(usize::MAX)
+        };
+        let Some(&(_, max_val)) = ranked_moves.first() else {
+            return vec![];
+        };
+
+        let mut top_moves: Vec<Coordinate> = ranked_moves
+            .into_iter()
+            .take_while(|&(_, val)| max_val - val <= TIE_TOLERANCE)
+            .map(|(coord, _)| coord)
+            .collect();
+
+        if self.rules.center_tie_break {
+            //doubled coordinates keep the center of even-sized boards on the integer grid
+            let (center_row, center_column) = (self.shots.height() - 1, self.shots.width() - 1);
+            top_moves.sort_by_key(|coord| {
+                (2 * coord.row).abs_diff(center_row).pow(2)
+                    + (2 * coord.column).abs_diff(center_column).pow(2)
+            });
+        }
+        top_moves
+    }
+
+    pub fn ranked_moves(&self, n: usize) -> Vec<(Coordinate, f32)> {
+        let mut moves = State::rank_untested(&self.heat_field, &self.shots);
+        moves.truncate(n);
+        moves
+    }
+
+    fn rank_untested(heat_field: &Field<f32>, shots: &Field<ShotStatus>) -> Vec<(Coordinate, f32)> {
+        let mut moves = heat_field
+            .iter_coords()
+            .zip(shots.get_grid().iter().flatten())
+            .filter(|(_, status)| status.is_untested())
+            .map(|((coord, &heat), _)| (coord, heat))
+            .collect::<Vec<_>>();
+
+        //sort_by is stable, so tied cells stay in row-major order
+        moves.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        moves
+    }
+
+    pub fn hunt_parity_moves(&self) -> Vec<(Coordinate, f32)> {
+        let ranked_moves = self.ranked_moves(usize::MAX);
+
+        let is_hunting = self
+            .shots
+            .find_all(|status| status.is_hit())
+            .is_empty();
+        let Some(&min_ship_length) = self.ships.iter().min() else {
+            return ranked_moves;
+        };
+        if !is_hunting {
+            return ranked_moves;
+        }
+
+        //every ship has to cover at least one cell of this grid
+        let parity_mask = Field::checkerboard(self.shots.width(), self.shots.height(), min_ship_length);
+        let parity_moves = ranked_moves
+            .iter()
+            .filter(|(coord, _)| parity_mask.get_grid()[coord.row][coord.column])
+            .copied()
+            .collect::<Vec<_>>();
+
+        if parity_moves.is_empty() {
+            ranked_moves
+        } else {
+            parity_moves
+        }
+    }
+
+    //the grid parity hunting sticks to, spaced by the smallest remaining ship. Only meaningful with ships left.
+    pub fn parity_mask(&self) -> Field<bool> {
+        let min_ship_length = self.ships.iter().min().copied().unwrap_or(1);
+        Field::checkerboard(self.shots.width(), self.shots.height(), min_ship_length)
+    }
+
+    //with a single fresh hit, the ship has to continue through one of its neighbors
+    pub fn target_moves(&self) -> Vec<(Coordinate, f32)> {
+        let clusters = heatmap::hit_clusters(&self.shots);
+        let [cluster] = &clusters[..] else {
+            return vec![];
+        };
+        let [hit] = cluster[..] else {
+            return vec![];
+        };
+
+        let neighbors = self.shots.neighbors(hit);
+        self.ranked_moves(usize::MAX)
+            .into_iter()
+            .filter(|(coord, _)| neighbors.contains(coord))
+            .collect()
+    }
+
+    //Every untested cell, ranked by how many placements a shot there is expected to rule out.
+    //Both outcomes are played out on a simulated board and the placements left afterwards counted, weighed by the chance of a hit.
+    pub fn info_gain_moves(&self) -> Vec<(Coordinate, f32)> {
+        let placements = heatmap::count_placements(&self.shots, &self.ships, &self.rules) as f32;
+
+        let mut moves = self
+            .ranked_moves(usize::MAX)
+            .into_iter()
+            .map(|(coord, heat)| {
+                //heat isn't a true chance under every combine mode, so keep it in range
+                let hit_chance = heat.clamp(0., 1.);
+                let remaining_after_miss = heatmap::count_placements(&self.simulated_shots(coord, ShotStatus::Miss), &self.ships, &self.rules) as f32;
+                //a hit leaves its cell open, so only the placements through it are still possible
+                let hit_shots = self.simulated_shots(coord, ShotStatus::Hit);
+                let remaining_after_hit = self
+                    .ships
+                    .iter()
+                    .map(|&ship_length| heatmap::placement_field(&hit_shots, ship_length, &self.rules).0.get_grid()[coord.row][coord.column])
+                    .sum::<usize>() as f32;
+                let expected_remaining = hit_chance * remaining_after_hit + (1. - hit_chance) * remaining_after_miss;
+                (coord, placements - expected_remaining)
+            })
+            .collect::<Vec<_>>();
+
+        //stable, so ties keep the order of ranked_moves
+        moves.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        moves
+    }
+
+    pub fn recommend_salvo(&self) -> Vec<Coordinate> {
+        let salvo_size = self.ships.len();
+        let mut salvo: Vec<Coordinate> = Vec::with_capacity(salvo_size);
+        let mut shots = self.shots.clone();
+        let mut heat_field = self.heat_field.clone();
+
+        //Every pick is assumed to miss and the heat recomputed before the next one,
+        //so later shots go where they tell us the most given the earlier ones.
+        while salvo.len() < salvo_size {
+            let Some(&(coord, _)) = State::rank_untested(&heat_field, &shots).first() else {
+                break;
+            };
+            salvo.push(coord);
+            if salvo.len() == salvo_size {
+                break;
+            }
+            shots.set_value(coord, &ShotStatus::Miss).unwrap();
+            heat_field = heatmap::gen_heat_field(&shots, &self.ships, &self.rules);
+            if let Some(prior) = &self.prior {
+                heat_field = heat_field.merge_field(prior, |heat, weight| heat * weight);
+            }
+        }
+
+        salvo
+    }
+
+    pub fn get_rules(&self) -> Rules {
+        self.rules
+    }
+
+    pub fn index_style(&self) -> IndexStyle {
+        self.rules.index_style
+    }
+
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+        self.full_recompute();
+    }
+
+    pub fn set_parity_hunt(&mut self, parity_hunt: bool) {
+        self.rules.parity_hunt = parity_hunt;
+        self.top_moves = self.generate_top_moves();
+        #[cfg(feature = "rand")]
+        self.pick_tied_move();
+    }
+
+    //one command per remaining action, oldest first
+    pub fn history_commands(&self) -> Vec<String> {
+        self.action_history.iter().map(|action| action.tx_command(self.rules.index_style)).collect()
+    }
+
+    //The history as a script that rebuilds the current board when run on a fresh state with the same rules.
+    //A sink that had several places to go would stop that script, so such histories are refused.
+    pub fn export_history(&self) -> Result<Vec<String>> {
+        let ambiguous_sink = self
+            .history_sinks
+            .iter()
+            .position(|ship| ship.as_ref().is_some_and(|ship| ship.ambiguous));
+        if let Some(idx) = ambiguous_sink {
+            return Err(anyhow!(
+                "Action {} sank a ship that could have been in more than one place, which a script can't replay.",
+                idx + 1
+            ));
+        }
+        Ok(self.history_commands())
+    }
+
+    pub fn get_last_action(&mut self) -> Result<Action> {
+        self.action_history.last().ok_or_else(|| anyhow!("No more actions to undo.")).copied()
+
+    }
+
+    pub fn get_last_matching_action(&self, action: Action) -> Result<Action> {
+        match action {
+            Action::Undo(_) => unreachable!("Undo-s or actions without associated data may never be appended to the action history."),
+            Action::Fire(_) | Action::Unfire(_) | Action::Hit(_) | Action::Unhit(_) | Action::Sink(_) | Action::Unsink(_) | Action::FireLine(_) | Action::UnfireLine(_) => {
+                self.action_history.iter().rev().find(|act| act.same_variant(&action)).ok_or_else(|| anyhow::anyhow!("Could not find last instance of action in history.")).copied()
+            }
+            
+        }
+    }
+
+    pub fn debug_print_state(&self) {
+        println!("Board State:");
+        print!("{}", self.board_ascii(true));
+        if !self.diagnostics.is_empty() {
+            println!("!!WARNING!!");
+            for diagnostic in &self.diagnostics {
+                println!("{}", diagnostic.tx_message(self.rules.index_style));
+            }
+            println!("Something is wrong. Continuing regardless.\n###############################");
+        }
+    }
+
+    pub fn diagnostics(&self) -> &[heatmap::Diagnostic] {
+        &self.diagnostics
+    }
+
+    //the board as printed by debug_print_state, one line per row. Without heat, untested cells are left blank.
+    //In plain mode, the best move is marked with '*' and the other top moves with '+'.
+    pub fn board_ascii(&self, show_heat: bool) -> String {
+        self.format_board(&self.shots, &self.heat_field, &self.top_moves, show_heat)
+    }
+
+    fn format_board(&self, shots: &Field<ShotStatus>, heat_field: &Field<f32>, top_moves: &[Coordinate], show_heat: bool) -> String {
+        use colored::Colorize;
+        use std::fmt::Write;
+
+        //the gradient spans up to the hottest cell, so it stays readable however spread out the heat is
+        let hottest = heat_field.get_grid().iter().flatten().copied().fold(0., f32::max);
+
+        let mut board = String::new();
+        for (row_idx, (heat_line, status_line)) in heat_field.get_grid().iter().zip(shots.get_grid().iter()).enumerate() {
+            for (column_idx, (heat, status)) in heat_line.iter().zip(status_line.iter()).enumerate() {
+                let cell = match status {
+                    ShotStatus::Untested => {
+                        let coord = Coordinate { row: row_idx, column: column_idx };
+                        let text = match (show_heat, self.percent) {
+                            (true, true) => format!("[{:>3}%]", to_percentage(*heat)),
+                            (true, false) => format!("[{heat:.2}]"),
+                            (false, _) => "[    ]".to_owned(),
+                        };
+                        let position = top_moves.iter().position(|&top_move_coord| coord == top_move_coord);
+                        match position {
+                            Some(0) if self.plain => format!("*{}*", &text[1..text.len() - 1]),
+                            Some(_) if self.plain => format!("+{}+", &text[1..text.len() - 1]),
+                            //the colors are taken by the heat, so top moves stand out by weight instead
+                            _ if self.gradient => {
+                                let (red, green, blue) = gradient_color(if hottest > 0. { heat / hottest } else { 0. });
+                                let text = text.truecolor(red, green, blue);
+                                match position {
+                                    Some(0) => text.bold().underline().to_string(),
+                                    Some(_) => text.bold().to_string(),
+                                    None => text.to_string(),
+                                }
+                            }
+                            Some(0) => text.red().to_string(),
+                            Some(_) => text.green().to_string(),
+                            None => text,
+                        }
+                    }
+                    ShotStatus::Hit => "[####]".to_owned(),
+                    ShotStatus::Miss => "[----]".to_owned(),
+                    ShotStatus::Sunk => "[||||]".to_owned(),
+                };
+                board.push_str(&cell);
+            }
+            writeln!(board).unwrap();
+        }
+        board
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coord(row: usize, column: usize) -> Coordinate {
+        Coordinate { row, column }
+    }
+
+    fn new_state(width: usize, height: usize, ships: &[usize]) -> State {
+        State::new(width, height, ships, Rules::classic()).unwrap()
+    }
+
+    #[test]
+    fn probability_at_reads_the_heat_field() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        assert_eq!(state.probability_at(coord(2, 2)), Some(state.get_heat_field().get_grid()[2][2]));
+        assert_eq!(state.probability_at(coord(5, 0)), None);
+        assert_eq!(state.probability_at(coord(0, 5)), None);
+
+        state.take_action(Action::Fire(Known(coord(2, 2)))).unwrap();
+        assert_eq!(state.probability_at(coord(2, 2)), Some(0.));
+    }
+
+    #[test]
+    fn ranked_moves_are_sorted_and_skip_resolved_cells() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        state.take_action(Action::Fire(Known(coord(2, 2)))).unwrap();
+
+        let ranked_moves = state.ranked_moves(usize::MAX);
+        assert_eq!(ranked_moves.len(), 24);
+        assert!(ranked_moves.iter().all(|&(coord, _)| state.status_at(coord) == Some(ShotStatus::Untested)));
+        assert!(ranked_moves.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        //ties stay in row-major order
+        assert!(ranked_moves
+            .windows(2)
+            .filter(|pair| pair[0].1 == pair[1].1)
+            .all(|pair| (pair[0].0.row, pair[0].0.column) < (pair[1].0.row, pair[1].0.column)));
+
+        assert!(state.ranked_moves(3) == ranked_moves[..3]);
+    }
+
+    #[test]
+    fn top_moves_are_the_tied_front_of_the_ranking() {
+        let state = new_state(5, 5, &[2, 3]);
+        let ranked_moves = state.ranked_moves(usize::MAX);
+        let best = ranked_moves[0].1;
+        let tied = ranked_moves
+            .iter()
+            .take_while(|&&(_, heat)| best - heat <= TIE_TOLERANCE)
+            .map(|&(coord, _)| coord)
+            .collect::<Vec<_>>();
+        assert!(state.get_top_moves() == tied);
+    }
+
+    #[test]
+    fn near_ties_are_all_top_moves() {
+        let mut state = new_state(3, 2, &[2]);
+        state.heat_field =
+            Field::from_flat(vec![0.5, 0.5 - 1e-7, 0.4, 0.5 + 1e-7, 0.3, 0.5 - 2e-7], 3, 2).unwrap();
+
+        let top_moves = state.generate_top_moves();
+        assert!(top_moves == vec![coord(1, 0), coord(0, 0), coord(0, 1), coord(1, 2)]);
+    }
+
+    #[test]
+    fn hunt_moves_stay_on_the_parity_grid() {
+        let mut state = new_state(7, 9, &[3, 4, 5]);
+        let hunt_moves = state.hunt_parity_moves();
+        assert!(!hunt_moves.is_empty());
+        assert!(hunt_moves.iter().all(|(coord, _)| (coord.row + coord.column) % 3 == 0));
+
+        state.set_parity_hunt(true);
+        assert!(state.get_top_moves().iter().all(|coord| (coord.row + coord.column) % 3 == 0));
+    }
+
+    fn take_all(state: &mut State, actions: &[Action]) {
+        for &action in actions {
+            state.take_action(action).unwrap();
+        }
+    }
+
+    #[test]
+    fn auto_sink_sinks_a_bounded_run_along_a_row() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        state.set_auto_sink(true);
+        take_all(
+            &mut state,
+            &[Action::Fire(Known(coord(0, 2))), Action::Hit(Known(coord(0, 3))), Action::Hit(Known(coord(0, 4)))],
+        );
+
+        assert_eq!(state.status_at(coord(0, 3)), Some(ShotStatus::Sunk));
+        assert_eq!(state.status_at(coord(0, 4)), Some(ShotStatus::Sunk));
+        assert_eq!(state.remaining_ships(), [3]);
+    }
+
+    #[test]
+    fn auto_sink_sinks_a_bounded_run_along_a_column() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        state.set_auto_sink(true);
+        take_all(
+            &mut state,
+            &[
+                Action::Fire(Known(coord(1, 0))),
+                Action::Hit(Known(coord(2, 0))),
+                Action::Hit(Known(coord(3, 0))),
+            ],
+        );
+        //the run could still go on downwards
+        assert_eq!(state.remaining_ships(), [2, 3]);
+
+        state.take_action(Action::Hit(Known(coord(4, 0)))).unwrap();
+        assert!((2..5).all(|row| state.status_at(coord(row, 0)) == Some(ShotStatus::Sunk)));
+        assert_eq!(state.remaining_ships(), [2]);
+    }
+
+    #[test]
+    fn hits_are_left_alone_without_auto_sink() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        take_all(
+            &mut state,
+            &[Action::Fire(Known(coord(0, 2))), Action::Hit(Known(coord(0, 3))), Action::Hit(Known(coord(0, 4)))],
+        );
+
+        assert_eq!(state.status_at(coord(0, 4)), Some(ShotStatus::Hit));
+        assert_eq!(state.remaining_ships(), [2, 3]);
+    }
+
+    #[test]
+    fn salvo_has_one_distinct_untested_shot_per_ship() {
+        let mut state = new_state(6, 6, &[2, 3, 3, 4]);
+        state.take_action(Action::Fire(Known(coord(2, 2)))).unwrap();
+
+        let salvo = state.recommend_salvo();
+        assert_eq!(salvo.len(), 4);
+        assert!(salvo.iter().all(|&coord| state.status_at(coord) == Some(ShotStatus::Untested)));
+        assert!(salvo.iter().enumerate().all(|(idx, coord)| !salvo[..idx].contains(coord)));
+    }
+
+    #[test]
+    fn salvo_stops_when_the_board_runs_out() {
+        let state = new_state(2, 1, &[1, 1, 1]);
+        assert_eq!(state.recommend_salvo().len(), 2);
+    }
+
+    #[test]
+    fn injected_resolver_picks_where_an_ambiguous_ship_sank() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        take_all(
+            &mut state,
+            &[Action::Hit(Known(coord(0, 0))), Action::Hit(Known(coord(0, 1))), Action::Hit(Known(coord(0, 2)))],
+        );
+        let offered = std::rc::Rc::new(std::cell::Cell::new(0));
+        let offered_in_resolver = offered.clone();
+        state.set_location_resolver(move |locations| {
+            offered_in_resolver.set(locations.len());
+            0
+        });
+
+        state.take_action(Action::Sink(Known(2))).unwrap();
+        assert_eq!(offered.get(), 2);
+        assert_eq!(state.status_at(coord(0, 0)), Some(ShotStatus::Sunk));
+        assert_eq!(state.status_at(coord(0, 1)), Some(ShotStatus::Sunk));
+        assert_eq!(state.status_at(coord(0, 2)), Some(ShotStatus::Hit));
+    }
+
+    #[test]
+    fn out_of_range_resolver_pick_fails_the_sink() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        take_all(
+            &mut state,
+            &[Action::Hit(Known(coord(0, 0))), Action::Hit(Known(coord(0, 1))), Action::Hit(Known(coord(0, 2)))],
+        );
+        state.set_location_resolver(|_| usize::MAX);
+
+        assert!(state.take_action(Action::Sink(Known(2))).is_err());
+        assert_eq!(state.remaining_ships(), [2, 3]);
+    }
+
+    #[test]
+    fn stats_follow_fires_hits_and_undos() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        take_all(
+            &mut state,
+            &[Action::Fire(Known(coord(0, 0))), Action::Hit(Known(coord(1, 0))), Action::Hit(Known(coord(1, 1)))],
+        );
+        let stats = state.stats();
+        assert_eq!((stats.shots_fired, stats.hits, stats.misses, stats.untested), (3, 2, 1, 22));
+        assert!((stats.hit_rate() - 2. / 3.).abs() < 1e-6);
+
+        state.undo(1).unwrap();
+        let stats = state.stats();
+        assert_eq!((stats.shots_fired, stats.hits, stats.misses), (2, 1, 1));
+
+        state.take_action(Action::Hit(Known(coord(1, 1)))).unwrap();
+        state.take_action(Action::Sink(Known(2))).unwrap();
+        let stats = state.stats();
+        assert_eq!((stats.hits, stats.ships_sunk, stats.ships_remaining), (2, 1, 1));
+
+        state.undo(1).unwrap();
+        let stats = state.stats();
+        assert_eq!((stats.hits, stats.ships_sunk, stats.ships_remaining), (2, 0, 2));
+    }
+
+    #[test]
+    fn sinking_the_last_ship_ends_the_game_until_undone() {
+        let mut state = new_state(3, 3, &[2, 1]);
+        take_all(&mut state, &[Action::Fire(Known(coord(1, 0))), Action::Hit(Known(coord(2, 2)))]);
+        assert!(matches!(state.take_action(Action::Sink(Known(1))), Result::Ok(ActionOutcome::Continued)));
+        take_all(&mut state, &[Action::Hit(Known(coord(0, 0))), Action::Hit(Known(coord(0, 1)))]);
+        let outcome = state.take_action(Action::Sink(Known(2))).unwrap();
+
+        assert!(matches!(outcome, ActionOutcome::GameOver));
+        assert!(state.is_complete());
+
+        state.undo(1).unwrap();
+        assert!(!state.is_complete());
+        assert_eq!(state.remaining_ships(), [2]);
+        assert_eq!(state.status_at(coord(0, 1)), Some(ShotStatus::Hit));
+    }
+
+    #[test]
+    fn target_moves_are_the_neighbors_of_a_lone_hit() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        assert!(state.target_moves().is_empty());
+
+        take_all(&mut state, &[Action::Hit(Known(coord(0, 2))), Action::Fire(Known(coord(0, 3)))]);
+        let mut targets = state.target_moves().iter().map(|(coord, _)| (coord.row, coord.column)).collect::<Vec<_>>();
+        targets.sort_unstable();
+        assert_eq!(targets, [(0, 1), (1, 2)]);
+
+        //with a second hit next to it, there's a direction to follow instead
+        state.take_action(Action::Hit(Known(coord(1, 2)))).unwrap();
+        assert!(state.target_moves().is_empty());
+    }
+
+    #[test]
+    fn a_run_of_hits_is_extended_along_its_row() {
+        let mut state = new_state(7, 7, &[2, 3, 4]);
+        take_all(&mut state, &[Action::Hit(Known(coord(3, 2))), Action::Hit(Known(coord(3, 3)))]);
+
+        let top_move = state.recommend().unwrap();
+        assert!(top_move == coord(3, 1) || top_move == coord(3, 4));
+    }
+
+    #[test]
+    fn undo_two_after_three_fires_leaves_one_miss() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        take_all(
+            &mut state,
+            &[Action::Fire(Known(coord(0, 0))), Action::Fire(Known(coord(1, 1))), Action::Fire(Known(coord(2, 2)))],
+        );
+
+        let undone = state.take_action(Action::Undo(Known(2)));
+        assert!(undone.is_ok());
+        assert_eq!(state.get_shots().count_where(ShotStatus::is_miss), 1);
+        assert_eq!(state.status_at(coord(0, 0)), Some(ShotStatus::Miss));
+    }
+
+    #[test]
+    fn undo_stops_early_when_the_history_runs_out() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        take_all(&mut state, &[Action::Fire(Known(coord(0, 0))), Action::Fire(Known(coord(1, 1)))]);
+
+        assert_eq!(state.undo(5).unwrap().len(), 2);
+        assert_eq!(state.get_shots().count_where(ShotStatus::is_miss), 0);
+        assert!(state.undo(1).is_err());
+    }
+
+    #[test]
+    fn new_rejects_fleets_that_cannot_be_placed() {
+        assert!(State::new(5, 4, &[6], Rules::classic()).is_err());
+        assert!(State::new(5, 4, &[2, 0], Rules::classic()).is_err());
+        assert!(State::new(0, 4, &[2], Rules::classic()).is_err());
+        assert!(State::new(5, 0, &[2], Rules::classic()).is_err());
+        assert!(State::new(5, 4, &[], Rules::classic()).is_err());
+        //as long as the longer side is fine
+        assert!(State::new(5, 1, &[5], Rules::classic()).is_ok());
+    }
+
+    #[test]
+    fn plain_board_ascii_marks_shots_and_the_top_move() {
+        let mut state = new_state(3, 2, &[2]);
+        state.set_plain(true);
+        take_all(&mut state, &[Action::Hit(Known(coord(0, 1))), Action::Fire(Known(coord(1, 1)))]);
+
+        assert_eq!(state.board_ascii(false), "*    *[####]+    +\n[    ][----][    ]\n");
+        assert_eq!(state.board_ascii(true), "*0.75*[####]+0.75+\n[0.25][----][0.25]\n");
+    }
+
+    #[test]
+    fn plain_board_ascii_has_no_escape_sequences() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        state.set_plain(true);
+        take_all(&mut state, &[Action::Hit(Known(coord(2, 2))), Action::Fire(Known(coord(0, 0)))]);
+
+        assert!(!state.board_ascii(true).contains('\x1b'));
+        assert!(!state.board_ascii(false).contains('\x1b'));
+    }
+
+    fn partially_hit_ship(rules: Rules) -> State {
+        let mut state = State::new(5, 5, &[2, 3], rules).unwrap();
+        take_all(
+            &mut state,
+            &[
+                Action::Hit(Known(coord(0, 0))),
+                Action::Hit(Known(coord(0, 1))),
+                Action::Fire(Known(coord(0, 3))),
+                Action::Fire(Known(coord(1, 0))),
+                Action::Fire(Known(coord(1, 1))),
+                Action::Fire(Known(coord(1, 2))),
+            ],
+        );
+        state
+    }
+
+    #[test]
+    fn strict_sink_needs_every_cell_hit() {
+        let mut state = partially_hit_ship(Rules::classic());
+        assert!(state.take_action(Action::Sink(Known(3))).is_err());
+        assert_eq!(state.remaining_ships(), [2, 3]);
+        assert_eq!(state.status_at(coord(0, 2)), Some(ShotStatus::Untested));
+    }
+
+    #[test]
+    fn lenient_sink_claims_untested_cells_next_to_the_hits() {
+        let mut state = partially_hit_ship(Rules { lenient_sink: true, ..Rules::classic() });
+        state.take_action(Action::Sink(Known(3))).unwrap();
+        assert_eq!(state.remaining_ships(), [2]);
+        assert!((0..3).all(|column| state.status_at(coord(0, column)) == Some(ShotStatus::Sunk)));
+        assert_eq!(state.sunk_ships()[0].never_hit_cells(), 1);
+    }
+
+    #[test]
+    fn autoplay_sinks_a_fixed_fleet_in_reasonable_time() {
+        let truth = crate::types::board::parse_board("
+            . . . . .
+            . X X X .
+            . . . . .
+            . . . . X
+            . . . . X
+        ").unwrap();
+        let mut state = new_state(5, 5, &[2, 3]);
+
+        let shots_fired = state.autoplay(&truth).unwrap();
+        assert!(state.is_complete());
+        //at least one shot per ship cell, and well short of shooting the whole board
+        assert!((5..=15).contains(&shots_fired), "took {shots_fired} shots");
+        assert!(truth.iter_coords().all(|(coord, status)| !status.is_hit() || state.status_at(coord) == Some(ShotStatus::Sunk)));
+    }
+
+    #[test]
+    fn autoplay_rejects_a_truth_board_of_another_size() {
+        let truth: Field<ShotStatus> = Field::new_default(4, 5);
+        assert!(new_state(5, 5, &[2, 3]).autoplay(&truth).is_err());
+    }
+
+    #[test]
+    fn center_tie_break_picks_the_center_when_every_cell_is_tied() {
+        //a single-cell ship heats every cell the same, so everything is tied
+        let row_major = new_state(5, 5, &[1]);
+        assert!(row_major.recommend() == Some(coord(0, 0)));
+
+        let centered = State::new(5, 5, &[1], Rules { center_tie_break: true, ..Rules::classic() }).unwrap();
+        assert!(centered.recommend() == Some(coord(2, 2)));
+        assert_eq!(centered.get_top_moves().len(), 25);
+    }
+
+    #[test]
+    fn undoing_a_sink_unsinks_the_ship() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        take_all(&mut state, &[Action::Hit(Known(coord(0, 0))), Action::Hit(Known(coord(0, 1))), Action::Fire(Known(coord(1, 0))), Action::Fire(Known(coord(1, 1))), Action::Fire(Known(coord(0, 2))), Action::Sink(Known(2))]);
+        assert_eq!(state.remaining_ships(), [3]);
+
+        state.undo(1).unwrap();
+        assert_eq!(state.remaining_ships(), [3, 2]);
+        assert_eq!(state.status_at(coord(0, 0)), Some(ShotStatus::Hit));
+        assert_eq!(state.status_at(coord(0, 1)), Some(ShotStatus::Hit));
+    }
+
+    #[test]
+    fn unsinking_a_ship_too_long_for_the_board_changes_nothing() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        assert!(state.take_action(Action::Unsink(Known(6))).is_err());
+        assert!(state.take_action(Action::Unsink(Known(0))).is_err());
+        assert_eq!(state.remaining_ships(), [2, 3]);
+    }
+
+    #[test]
+    fn unfiring_a_fire_on_an_empty_cell_leaves_it_untested() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        state.take_action(Action::Fire(Known(coord(1, 1)))).unwrap();
+        state.take_action(Action::Unfire(Known(coord(1, 1)))).unwrap();
+        assert_eq!(state.status_at(coord(1, 1)), Some(ShotStatus::Untested));
+    }
+
+    #[test]
+    fn manual_unfire_of_a_hit_is_rejected() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        state.take_action(Action::Hit(Known(coord(1, 1)))).unwrap();
+        assert!(state.take_action(Action::Unfire(Known(coord(1, 1)))).is_err());
+        assert_eq!(state.status_at(coord(1, 1)), Some(ShotStatus::Hit));
+    }
+
+    #[test]
+    fn undoing_a_fire_restores_what_it_replaced() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        state.take_action(Action::Hit(Known(coord(1, 1)))).unwrap();
+        state.take_action(Action::Fire(Known(coord(1, 1)))).unwrap();
+        assert_eq!(state.status_at(coord(1, 1)), Some(ShotStatus::Miss));
+
+        state.undo(1).unwrap();
+        assert_eq!(state.status_at(coord(1, 1)), Some(ShotStatus::Hit));
+    }
+
+    #[test]
+    fn recommend_finds_the_last_untested_cell() {
+        let mut state = new_state(3, 3, &[2]);
+        state.set_shots(crate::types::board::parse_board("ooo\nX.o\nooo").unwrap()).unwrap();
+        assert!(state.recommend() == Some(coord(1, 1)));
+    }
+
+    #[test]
+    fn recommend_gives_none_once_every_cell_is_resolved() {
+        let mut state = new_state(3, 3, &[2]);
+        state.set_shots(crate::types::board::parse_board("ooo\nXoo\nooo").unwrap()).unwrap();
+        assert!(state.recommend().is_none());
+    }
+
+    #[test]
+    fn ships_of_the_same_length_are_sunk_as_different_ships() {
+        let mut state = new_state(5, 5, &[3, 3]);
+        take_all(&mut state, &[Action::Hit(Known(coord(0, 0))), Action::Hit(Known(coord(0, 1))), Action::Hit(Known(coord(0, 2))), Action::Sink(Known(3))]);
+        take_all(&mut state, &[Action::Hit(Known(coord(1, 0))), Action::Hit(Known(coord(1, 1))), Action::Hit(Known(coord(1, 2))), Action::Sink(Known(3))]);
+
+        let sunk_ships = state.sunk_ships();
+        assert_eq!(sunk_ships.iter().map(|ship| ship.id).collect::<Vec<_>>(), [0, 1]);
+        assert!(sunk_ships[1].location == [coord(1, 0), coord(1, 1), coord(1, 2)]);
+        assert!(state.is_complete());
+    }
+
+    #[test]
+    fn sinking_a_ship_cools_its_footprint_and_surroundings() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        take_all(&mut state, &[Action::Hit(Known(coord(0, 0))), Action::Hit(Known(coord(0, 1)))]);
+        let heat = |state: &State, row, column| state.probability_at(coord(row, column)).unwrap();
+        let (end_before, below_before) = (heat(&state, 0, 2), heat(&state, 1, 0));
+
+        state.take_action(Action::Sink(Known(2))).unwrap();
+        assert_eq!(state.remaining_ships(), [3]);
+        assert_eq!(state.probability_at(coord(0, 0)), Some(0.));
+        assert_eq!(state.probability_at(coord(0, 1)), Some(0.));
+        assert!(heat(&state, 0, 2) < end_before);
+        assert!(heat(&state, 1, 0) < below_before);
+    }
+
+    #[test]
+    fn percentages_round_and_never_pass_100() {
+        let mut state = new_state(3, 2, &[2]);
+        state.set_plain(true);
+        state.set_percent(true);
+        state.take_action(Action::Hit(Known(coord(0, 1)))).unwrap();
+        state.heat_field = Field::from_flat(vec![0.237, 0.9, 1. + 1e-6, 0.004, 0.005, 0.], 3, 2).unwrap();
+        state.top_moves.clear();
+
+        assert_eq!(state.probability_percentages().get_grid()[..], [vec![24, 0, 100], vec![0, 1, 0]]);
+        assert!(state.board_ascii(true).starts_with("[ 24%][####][100%]"));
+    }
+
+    #[test]
+    fn simulate_leaves_the_state_alone() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        state.take_action(Action::Fire(Known(coord(0, 0)))).unwrap();
+        let shots_before = state.get_shots().clone();
+
+        for outcome in [ShotStatus::Miss, ShotStatus::Hit] {
+            let simulated = state.simulate(coord(2, 2), outcome);
+            assert!(state.get_shots().equal(&shots_before));
+            assert!(!simulated.equal(state.get_heat_field()));
+        }
+        assert_eq!(state.status_at(coord(2, 2)), Some(ShotStatus::Untested));
+    }
+
+    #[test]
+    fn most_informative_move_on_an_open_board_is_near_the_center() {
+        let mut state = new_state(7, 7, &[2, 3, 4]);
+        state.take_action(Action::Fire(Known(coord(0, 6)))).unwrap();
+
+        let info_gain_moves = state.info_gain_moves();
+        assert_eq!(info_gain_moves.len(), 48);
+        assert!(info_gain_moves.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        let (best, _) = info_gain_moves[0];
+        assert!(best.row.abs_diff(3) <= 1 && best.column.abs_diff(3) <= 1, "best move at ({}, {})", best.row, best.column);
+    }
+
+    #[test]
+    fn a_shot_certain_to_hit_tells_nothing() {
+        //the two placements of the ship both cover the middle, and each end settles which one it is
+        let state = new_state(3, 1, &[2]);
+        let gains = state.info_gain_moves().into_iter().map(|(coord, gain)| ((coord.row, coord.column), gain)).collect::<Vec<_>>();
+        assert_eq!(gains.len(), 3);
+        assert_eq!(gains[2], ((0, 1), 0.));
+        assert!(gains[..2].iter().all(|&(_, gain)| (gain - 1.).abs() < 1e-6), "{gains:?}");
+    }
+
+    //the first few recommendations when always firing at the last one, on a board where every cell is tied
+    #[cfg(feature = "rand")]
+    fn seeded_picks(seed: Option<u64>) -> Vec<(usize, usize)> {
+        let mut state = new_state(5, 5, &[1]);
+        if let Some(seed) = seed {
+            state.set_seed(seed);
+        }
+        (0..5).map(|_| {
+            let pick = state.recommend().unwrap();
+            state.take_action(Action::Fire(Known(pick))).unwrap();
+            (pick.row, pick.column)
+        }).collect()
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn same_seed_gives_the_same_picks() {
+        assert_eq!(seeded_picks(Some(42)), seeded_picks(Some(42)));
+        let picks = (0..8).map(|seed| seeded_picks(Some(seed))).collect::<Vec<_>>();
+        assert!(picks.iter().any(|other| *other != picks[0]));
+        //without a seed, the first tied cell is taken every time
+        assert_eq!(seeded_picks(None), [(0, 0), (0, 1), (0, 2), (0, 3), (0, 4)]);
+    }
+
+    #[test]
+    fn raising_the_threshold_only_shrinks_the_mask() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        take_all(&mut state, &[Action::Hit(Known(coord(2, 2))), Action::Fire(Known(coord(0, 0)))]);
+
+        let mut previous = state.likely_mask(0.);
+        assert_eq!(previous.count_where(|&likely| likely), 23);
+        for step in 1..=20 {
+            let mask = state.likely_mask(step as f32 * 0.05);
+            assert!(mask.iter_coords().all(|(coord, &likely)| !likely || previous.get_grid()[coord.row][coord.column]));
+            previous = mask;
+        }
+        assert!(state.likely_mask(1.01).iter_coords().all(|(_, &likely)| !likely));
+    }
+
+    #[test]
+    fn hemmed_in_cells_only_fit_short_ships() {
+        let mut state = new_state(7, 7, &[5, 2, 3, 4]);
+        //(1, 1) sits in a gap of two, both across and down
+        take_all(&mut state, &[Action::Fire(Known(coord(1, 0))), Action::Fire(Known(coord(1, 3))), Action::Fire(Known(coord(0, 1))), Action::Fire(Known(coord(3, 1)))]);
+
+        assert_eq!(state.possible_ship_lengths_at(coord(1, 1)), [2]);
+        assert_eq!(state.possible_ship_lengths_at(coord(5, 5)), [2, 3, 4, 5]);
+        assert!(state.possible_ship_lengths_at(coord(1, 0)).is_empty());
+    }
+
+    #[test]
+    fn gradient_board_keeps_the_heat_values() {
+        colored::control::set_override(false);
+        let mut state = new_state(3, 2, &[2]);
+        state.set_gradient(true);
+        take_all(&mut state, &[Action::Hit(Known(coord(0, 1))), Action::Fire(Known(coord(1, 1)))]);
+
+        let board = state.board_ascii(true);
+        //the override is global, so it's lifted again before anything can fail
+        colored::control::unset_override();
+        assert_eq!(board, "[0.75][####][0.75]\n[0.25][----][0.25]\n");
+    }
+
+    #[test]
+    fn undoing_a_hit_after_a_fire_leaves_the_miss() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        state.take_action(Action::Fire(Known(coord(3, 1)))).unwrap();
+        assert_eq!(state.status_at(coord(3, 1)), Some(ShotStatus::Miss));
+        state.take_action(Action::Hit(Known(coord(3, 1)))).unwrap();
+        assert_eq!(state.status_at(coord(3, 1)), Some(ShotStatus::Hit));
+
+        state.undo(1).unwrap();
+        assert_eq!(state.status_at(coord(3, 1)), Some(ShotStatus::Miss));
+    }
+
+    #[test]
+    fn unhit_turns_a_hit_into_a_miss() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        state.take_action(Action::Hit(Known(coord(3, 1)))).unwrap();
+        state.take_action(Action::Unhit(Known(coord(3, 1)))).unwrap();
+        assert_eq!(state.status_at(coord(3, 1)), Some(ShotStatus::Miss));
+        assert!(state.take_action(Action::Unhit(Known(coord(3, 1)))).is_err());
+    }
+
+    #[test]
+    fn fires_against_a_known_layout_resolve_themselves() {
+        let truth = crate::types::board::parse_board("X X . . .\n. . . . .\n. . X X X\n. . . . .\n. . . . .").unwrap();
+        let mut state = new_state(5, 5, &[2, 3]);
+        state.set_truth(truth).unwrap();
+
+        state.take_action(Action::Fire(Known(coord(2, 3)))).unwrap();
+        assert_eq!(state.status_at(coord(2, 3)), Some(ShotStatus::Hit));
+        state.take_action(Action::Fire(Known(coord(1, 3)))).unwrap();
+        assert_eq!(state.status_at(coord(1, 3)), Some(ShotStatus::Miss));
+
+        //the second hit completes the ship, which sinks it
+        take_all(&mut state, &[Action::Fire(Known(coord(0, 0))), Action::Fire(Known(coord(0, 1)))]);
+        assert_eq!(state.status_at(coord(0, 0)), Some(ShotStatus::Sunk));
+        assert_eq!(state.remaining_ships(), [3]);
+    }
+
+    fn two_locations() -> Vec<Vec<Coordinate>> {
+        vec![vec![coord(0, 0), coord(0, 1)], vec![coord(1, 0), coord(1, 1)]]
+    }
+
+    #[test]
+    fn location_prompt_gives_up_at_the_end_of_input() {
+        let pick = |answers: &str| State::read_ship_location(&two_locations(), IndexStyle::ChessOneIndexed, &mut answers.as_bytes());
+        assert_eq!(pick(""), usize::MAX);
+        //invalid answers are asked again, until there are none left
+        assert_eq!(pick("7\n\nsomewhere\n"), usize::MAX);
+        assert_eq!(pick("0\n2\n"), 1);
+    }
+
+    #[test]
+    fn undoing_a_fireline_only_clears_the_cells_it_fired_at() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        take_all(&mut state, &[Action::Fire(Known(coord(2, 0))), Action::Hit(Known(coord(2, 3)))]);
+        let shots_before = state.get_shots().clone();
+
+        state.take_action(Action::FireLine(Known(Line { axis: field::helpers::Axis::Row, index: 2 }))).unwrap();
+        assert!((0..5).all(|column| matches!(state.status_at(coord(2, column)), Some(ShotStatus::Miss | ShotStatus::Hit))));
+        assert_eq!(state.status_at(coord(2, 3)), Some(ShotStatus::Hit));
+        assert_eq!(state.status_at(coord(1, 1)), Some(ShotStatus::Untested));
+
+        state.undo(1).unwrap();
+        assert!(state.get_shots().equal(&shots_before));
+    }
+
+    #[test]
+    fn forced_cells_get_full_heat() {
+        let mut state = new_state(4, 3, &[2, 3]);
+        state.set_shots(crate::types::board::parse_board("..oo\nXoo.\noooX").unwrap()).unwrap();
+        assert_eq!(state.remaining_untested_count(), 3);
+
+        for cell in [coord(0, 0), coord(0, 1), coord(1, 3)] {
+            assert_eq!(state.probability_at(cell), Some(1.));
+        }
+        assert_eq!(state.probability_at(coord(1, 0)), Some(0.));
+    }
+
+    #[test]
+    fn last_matching_action_keeps_its_arguments() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        take_all(&mut state, &[Action::Fire(Known(coord(0, 0))), Action::Hit(Known(coord(2, 2))), Action::Fire(Known(coord(4, 4)))]);
+
+        assert_eq!(state.get_last_matching_action(Action::Fire(Unknown)).unwrap(), Action::Fire(Known(coord(4, 4))));
+        assert_eq!(state.get_last_matching_action(Action::Hit(Unknown)).unwrap(), Action::Hit(Known(coord(2, 2))));
+        assert!(state.get_last_matching_action(Action::Sink(Unknown)).is_err());
+    }
+
+    #[test]
+    fn autoplay_gives_up_at_the_turn_limit() {
+        //a truth board without ships can never be won, so only the limit ends the game
+        let truth: Field<ShotStatus> = Field::new_default(5, 5);
+        let mut state = new_state(5, 5, &[2, 3]);
+        state.set_max_turns(Some(5));
+
+        let err = state.autoplay(&truth).unwrap_err();
+        assert_eq!(err.to_string(), "Gave up after 5 turns with ships left afloat.");
+        assert_eq!(state.get_shots().count_where(ShotStatus::is_miss), 5);
+    }
+
+    #[test]
+    fn undoing_an_ambiguous_sink_puts_the_hits_back() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        take_all(&mut state, &[Action::Hit(Known(coord(0, 0))), Action::Hit(Known(coord(0, 1))), Action::Hit(Known(coord(0, 2)))]);
+        state.set_location_resolver(|locations| locations.len() - 1);
+        state.take_action(Action::Sink(Known(2))).unwrap();
+        assert_eq!(state.get_shots().count_where(ShotStatus::is_sunk), 2);
+
+        state.undo(1).unwrap();
+        assert!((0..3).all(|column| state.status_at(coord(0, column)) == Some(ShotStatus::Hit)));
+        assert_eq!(state.remaining_ships(), [3, 2]);
+    }
+
+    #[test]
+    fn more_hits_than_ship_cells_is_reported_as_impossible() {
+        let mut state = new_state(5, 5, &[2]);
+        assert_eq!(state.fleet_tonnage(), 2);
+        state.set_shots(crate::types::board::parse_board("X.X..\n.....\n..X..\n.....\n.....").unwrap()).unwrap();
+
+        let messages = state.diagnostics().iter().map(|diagnostic| diagnostic.tx_message(IndexStyle::ChessOneIndexed)).collect::<Vec<_>>();
+        assert!(messages.contains(&"The board is impossible: 3 hits, but only 2 ship cells remain.".to_owned()), "{messages:?}");
+    }
+
+    #[test]
+    fn state_from_a_parsed_board_matches_replaying_it() {
+        let shots = crate::types::board::parse_board("o . . . .\n. X . . .\n. X . o .\n. . . . .\n. . . . o").unwrap();
+        let from_board = State::from_board(shots, &[2, 3], Rules::classic()).unwrap();
+
+        let mut replayed = new_state(5, 5, &[2, 3]);
+        take_all(&mut replayed, &[Action::Fire(Known(coord(0, 0))), Action::Hit(Known(coord(1, 1))), Action::Hit(Known(coord(2, 1))), Action::Fire(Known(coord(2, 3))), Action::Fire(Known(coord(4, 4)))]);
+
+        assert!(from_board.get_shots().equal(replayed.get_shots()));
+        assert!(from_board.get_heat_field().equal(replayed.get_heat_field()));
+        assert!(from_board.get_top_moves() == replayed.get_top_moves());
+    }
+
+    #[test]
+    fn log_gets_a_line_per_action() {
+        let path = std::env::temp_dir().join(format!("battleships-{}-log", std::process::id()));
+        let mut state = new_state(5, 5, &[2, 3]);
+        state.set_log(Some(path.clone()));
+        take_all(&mut state, &[Action::Fire(Known(coord(0, 0))), Action::Hit(Known(coord(2, 2)))]);
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let names = log.lines().map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["action"].as_str().unwrap().to_owned()).collect::<Vec<_>>();
+        assert_eq!(names, ["fire", "hit"]);
+    }
+
+    #[test]
+    fn scrubbing_through_the_history_rebuilds_each_board() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        let actions = [Action::Fire(Known(coord(4, 4))), Action::Hit(Known(coord(0, 0))), Action::Hit(Known(coord(0, 1))), Action::Hit(Known(coord(0, 2))), Action::Sink(Known(2)), Action::Fire(Known(coord(2, 2)))];
+        state.set_location_resolver(|_| 1);
+        let mut boards = vec![state.get_shots().clone()];
+        for action in actions {
+            state.take_action(action).unwrap();
+            boards.push(state.get_shots().clone());
+        }
+        //the sink had two places to go, replaying it must take the recorded one instead of asking again
+        state.set_location_resolver(|_| panic!("replaying asked where the ship sank"));
+
+        for step in [2, 0, 5, 3, 1, 4, 6] {
+            state.replay_to(step).unwrap();
+            assert!(state.get_shots().equal(&boards[step]), "step {step}");
+        }
+        assert_eq!(state.remaining_ships(), [3]);
+        assert!(state.replay_to(7).is_err());
+
+        state.undo_all().unwrap();
+        assert!(state.get_shots().equal(&boards[0]));
+        assert_eq!(state.remaining_ships(), [2, 3]);
+    }
+
+    fn diagonal_state() -> State {
+        State::new(5, 5, &[2, 3], Rules { allow_diagonal: true, ..Rules::classic() }).unwrap()
+    }
+
+    #[test]
+    fn sinking_finds_a_ship_lying_along_a_diagonal() {
+        let mut state = diagonal_state();
+        state.set_location_resolver(|_| panic!("the diagonal was the only place the ship could be"));
+        take_all(&mut state, &[Action::Hit(Known(coord(1, 3))), Action::Hit(Known(coord(2, 2))), Action::Hit(Known(coord(3, 1))), Action::Sink(Known(3))]);
+
+        for cell in [coord(1, 3), coord(2, 2), coord(3, 1)] {
+            assert_eq!(state.status_at(cell), Some(ShotStatus::Sunk));
+        }
+        assert_eq!(state.remaining_ships(), [2]);
+    }
+
+    #[test]
+    fn auto_sink_sinks_a_bounded_run_along_a_diagonal() {
+        let mut state = diagonal_state();
+        state.set_auto_sink(true);
+        take_all(
+            &mut state,
+            &[Action::Fire(Known(coord(0, 0))), Action::Fire(Known(coord(4, 4))), Action::Hit(Known(coord(1, 1))), Action::Hit(Known(coord(2, 2))), Action::Hit(Known(coord(3, 3)))],
+        );
+
+        for cell in [coord(1, 1), coord(2, 2), coord(3, 3)] {
+            assert_eq!(state.status_at(cell), Some(ShotStatus::Sunk));
+        }
+        assert_eq!(state.remaining_ships(), [2]);
+    }
+
+    #[test]
+    fn a_failed_replay_leaves_the_game_as_it_was() {
+        let mut state = new_state(5, 5, &[2, 3]);
+        take_all(&mut state, &[Action::Fire(Known(coord(4, 4))), Action::Hit(Known(coord(0, 0))), Action::Fire(Known(coord(2, 2)))]);
+        state.replay_to(1).unwrap();
+        //a step that can't be taken again: there is no miss at 1 1 to take back
+        state.future_actions.push((Action::Unfire(Known(coord(1, 1))), None));
+        let board = state.get_shots().clone();
+
+        assert!(state.replay_to(4).is_err());
+        assert!(state.get_shots().equal(&board));
+        assert_eq!(state.timeline_len(), 4);
+        state.replay_to(3).unwrap();
+        assert_eq!(state.status_at(coord(2, 2)), Some(ShotStatus::Miss));
+        assert_eq!(state.status_at(coord(0, 0)), Some(ShotStatus::Hit));
     }
 }
@@ -1,9 +1,11 @@
 use Argument::{Known, Unknown};
 use crate::types::Coordinate;
-use crate::types::Printable;
+use crate::types::{IndexStyle, Printable};
+use field::helpers::Axis;
+use std::fmt;
 use strum_macros::EnumIter;
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, PartialEq)]
 pub enum Argument<T: Clone + Copy + Default> {
     //Basically an Option<>, just with a more fitting name
     Known(T),
@@ -11,14 +13,65 @@ pub enum Argument<T: Clone + Copy + Default> {
     Unknown,
 }
 
-#[derive(EnumIter, Clone, Copy)]
+impl<T: Clone + Copy + Default> Argument<T> {
+    pub fn known_or(self, default: T) -> T {
+        match self {
+            Known(value) => value,
+            Unknown => default,
+        }
+    }
+}
+
+//a whole row or column of the board
+#[derive(Clone, Copy)]
+pub struct Line {
+    pub axis: Axis,
+    pub index: usize,
+}
+
+//Axis doesn't compare on its own, so this goes by the variants
+impl PartialEq for Line {
+    fn eq(&self, other: &Line) -> bool {
+        self.index == other.index
+            && matches!(
+                (self.axis, other.axis),
+                (Axis::Row, Axis::Row) | (Axis::Column, Axis::Column)
+            )
+    }
+}
+
+//Argument needs a default, and Axis has none of its own
+impl Default for Line {
+    fn default() -> Line {
+        Line {
+            axis: Axis::Row,
+            index: 0,
+        }
+    }
+}
+
+impl Line {
+    //same as coordinates, the index is shown the way the index style counts
+    pub fn printable(&self, style: IndexStyle) -> String {
+        let index = self.index + style.first_index();
+        match self.axis {
+            Axis::Row => format!("row {index}"),
+            Axis::Column => format!("column {index}"),
+        }
+    }
+}
+
+#[derive(EnumIter, Clone, Copy, PartialEq)]
 pub enum Action {
     Fire(Argument<Coordinate>),
     Hit(Argument<Coordinate>),
     Sink(Argument<usize>),
     Unfire(Argument<Coordinate>),
+    Unhit(Argument<Coordinate>),
     Unsink(Argument<usize>),
-    Undo,
+    FireLine(Argument<Line>),
+    UnfireLine(Argument<Line>),
+    Undo(Argument<usize>),
 }
 
 impl Action {
@@ -28,27 +81,42 @@ impl Action {
             Action::Hit(_) => "hit",
             Action::Sink(_) => "sink",
             Action::Unfire(_) => "unfire",
+            Action::Unhit(_) => "unhit",
             Action::Unsink(_) => "unsink",
-            Action::Undo => "undo",
+            Action::FireLine(_) => "fireline",
+            Action::UnfireLine(_) => "unfireline",
+            Action::Undo(_) => "undo",
         }
     }
 
+    //the same kind of action, whatever the arguments. Use == to compare the arguments as well.
+    pub fn same_variant(&self, other: &Action) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
     pub fn opposite(&self) -> Action {
         match *self {
             Action::Fire(content) => Action::Unfire(content),
-            Action::Hit(content) => Action::Unfire(content),
+            Action::Hit(content) => Action::Unhit(content),
             Action::Sink(content) => Action::Unsink(content),
             Action::Unfire(content) => Action::Fire(content),
+            Action::Unhit(content) => Action::Hit(content),
             Action::Unsink(content) => Action::Sink(content),
-            Action::Undo => unreachable!("There exists no opposite of 'Undo'."),
+            Action::FireLine(content) => Action::UnfireLine(content),
+            Action::UnfireLine(content) => Action::FireLine(content),
+            Action::Undo(_) => unreachable!("There exists no opposite of 'Undo'."),
         }
     }
 
     pub fn expected_arg_count(&self) -> usize {
         match *self {
-            Action::Fire(_) | Action::Unfire(_) | Action::Hit(_) => 2,
-            Action::Sink(_) | Action::Unsink(_) => 1,
-            Action::Undo => 0,
+            Action::Fire(_)
+            | Action::Unfire(_)
+            | Action::Hit(_)
+            | Action::Unhit(_)
+            | Action::FireLine(_)
+            | Action::UnfireLine(_) => 2,
+            Action::Sink(_) | Action::Unsink(_) | Action::Undo(_) => 1,
         }
     }
 
@@ -57,45 +125,167 @@ impl Action {
             Self::Fire(_)
             | Action::Unfire(_)
             | Action::Hit(_)
+            | Action::Unhit(_)
             | Action::Unsink(_)
-            | Action::Undo => true,
-            Action::Sink(_) => false,
+            | Action::UnfireLine(_)
+            | Action::Undo(_) => true,
+            Action::Sink(_) | Action::FireLine(_) => false,
         }
     }
 
-    pub fn tx_syntax_help(&self) -> String {
+    pub fn tx_syntax_help(&self, style: IndexStyle) -> String {
+        let (args, hint, index_hint) = (style.tx_coordinate_args(), style.tx_coordinate_hint(), style.tx_index_hint());
         match self {
-                    Action::Fire(_) => "'fire <column> <row>' [1-index] Fires at the specified coordinate.\n\tDefault: Executes most recent recommendation.".to_owned(),
-                    Action::Hit(_) => "'hit <column> <row>' [1-index] Marks the specified coordinate as hit.\n\tDefault: Marks the most recently fired at coodinate as hit.".to_owned(),
+                    Action::Fire(_) => format!("'fire {args}' {hint} Fires at the specified coordinate.\n\tDefault: Executes most recent recommendation."),
+                    Action::Hit(_) => format!("'hit {args}' {hint} Marks the specified coordinate as hit.\n\tDefault: Marks the most recently fired at coodinate as hit, or the recommended move if nothing was fired at yet."),
                     Action::Sink(_) => "'sink <ship length>' Removes one ship of the specified length from the list.\n\tUnfortunately the length cannot logically be inferred.".to_owned(),
-                    Action::Unfire(_) => "'unfire <column> <row>' [1-index] Removes specified fireing marker.\n\tDefault: Undoes most recent fire command.".to_owned(),
-                    Action::Unsink(_) => "'unsink <ship length>' Adds one ship of the specified length to the list.\n\tDefault: Undoes the most recent sink command.".to_owned(),
-                    Action::Undo => "'undo' Undoes the most recent action.".to_owned(),
+                    Action::Unfire(_) => format!("'unfire {args}' {hint} Removes specified fireing marker.\n\tDefault: Undoes most recent fire command."),
+                    Action::Unhit(_) => format!("'unhit {args}' {hint} Turns the specified hit marker back into a miss.\n\tDefault: Undoes the most recent hit command."),
+                    Action::Unsink(_) => "'unsink <ship length>' Adds one ship of the specified length to the list.\n\tIf one of that length was sunk, its cells go back to how they were before.\n\tDefault: Undoes the most recent sink command.".to_owned(),
+                    Action::FireLine(_) => format!("'fireline <row|column> <index>' {index_hint} Marks every untested coordinate of the row or column as a miss, as a single action.\n\tThe line cannot be inferred."),
+                    Action::UnfireLine(_) => format!("'unfireline <row|column> <index>' {index_hint} Removes every miss marker of the row or column.\n\tDefault: Undoes the most recent fireline command."),
+                    Action::Undo(_) => "'undo <count>' Undoes the specified number of most recent actions.\n\tDefault: Undoes only the most recent action.".to_owned(),
                 }
     }
 
-    pub fn tx_success(&self) -> String {
+    //the command that takes this action again, as typed by the user
+    pub fn tx_command(&self, style: IndexStyle) -> String {
+        match self {
+            Action::Fire(Known(coordinate))
+            | Action::Unfire(Known(coordinate))
+            | Action::Hit(Known(coordinate))
+            | Action::Unhit(Known(coordinate)) => {
+                let (column, row) = coordinate.to_user(style);
+                format!("{} {column} {row}", self.name())
+            }
+            Action::Sink(Known(ship_length)) | Action::Unsink(Known(ship_length)) => {
+                format!("{} {ship_length}", self.name())
+            }
+            Action::FireLine(Known(line)) | Action::UnfireLine(Known(line)) => {
+                format!("{} {}", self.name(), line.printable(style))
+            }
+            Action::Undo(Known(count)) => format!("{} {count}", self.name()),
+            _ => self.name().to_owned(),
+        }
+    }
+
+    //the message for taking this action back. Mostly that of the opposite action,
+    //but an undone hit gets back whatever the cell was before, which needn't be the miss unhit leaves.
+    pub fn tx_undone(&self, style: IndexStyle) -> String {
+        match self {
+            Action::Hit(Known(coordinate)) => format!(
+                "Removed hit marker at {}, it is back to how it was before the hit.",
+                coordinate.printable(style)
+            ),
+            //only the cells it fired at are cleared, earlier misses stay
+            Action::FireLine(Known(line)) => {
+                format!("Put {} back to how it was before.", line.printable(style))
+            }
+            _ => self.opposite().tx_success(style),
+        }
+    }
+
+    pub fn tx_success(&self, style: IndexStyle) -> String {
         match self {
-            Action::Fire(Known(coordinate)) => format!("Fired at {}.", coordinate.printable()),
+            Action::Fire(Known(coordinate)) => format!("Fired at {}.", coordinate.printable(style)),
             Action::Hit(Known(coordinate)) => {
-                format!("Set hit marker at {}.", coordinate.printable())
+                format!("Set hit marker at {}.", coordinate.printable(style))
             }
             Action::Sink(Known(ship_length)) => format!("Sunk a ship of length {ship_length}." ),
             Action::Unfire(Known(coordinate)) => {
-                format!("Removed fire marker at {}.", coordinate.printable())
+                format!("Removed fire marker at {}.", coordinate.printable(style))
+            }
+            Action::Unhit(Known(coordinate)) => {
+                format!("Removed hit marker at {}, it is a miss again.", coordinate.printable(style))
             }
             Action::Unsink(Known(ship_length)) => {
                 format!("Added a ship of length {ship_length} to the roster." )
             }
-            Action::Undo => unreachable!(
+            Action::FireLine(Known(line)) => {
+                format!("Fired at every untested coordinate of {}.", line.printable(style))
+            }
+            Action::UnfireLine(Known(line)) => {
+                format!("Removed every fire marker of {}.", line.printable(style))
+            }
+            Action::Undo(_) => unreachable!(
                 "When undoing, the success message printed should be that of the action executed."
             ),
 
             Action::Fire(Unknown) 
             | Action::Unfire(Unknown) 
             | Action::Hit(Unknown) 
+            | Action::Unhit(Unknown) 
             | Action::Sink(Unknown) 
-            | Action::Unsink(Unknown) => unreachable!("Since actions with unknown args cannot be executed, there should not be a success message.")
+            | Action::Unsink(Unknown)
+            | Action::FireLine(Unknown)
+            | Action::UnfireLine(Unknown) => unreachable!("Since actions with unknown args cannot be executed, there should not be a success message.")
+        }
+    }
+}
+
+//the command as typed in the default index style, e.g. "fire 3 4". Actions with unknown arguments show just their name.
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.tx_command(IndexStyle::default()))
+    }
+}
+
+//Coordinate has no Debug of its own, so this goes by the command as well
+impl fmt::Debug for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Action({self})")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn undone(action: Action) -> String {
+        action.tx_undone(IndexStyle::ChessOneIndexed)
+    }
+
+    #[test]
+    fn undo_messages_name_what_was_taken_back() {
+        let coord = Coordinate { row: 1, column: 2 };
+        let line = Line { axis: Axis::Row, index: 3 };
+
+        assert_eq!(undone(Action::Fire(Known(coord))), "Removed fire marker at [3, 2].");
+        assert_eq!(undone(Action::Hit(Known(coord))), "Removed hit marker at [3, 2], it is back to how it was before the hit.");
+        assert_eq!(undone(Action::Sink(Known(3))), "Added a ship of length 3 to the roster.");
+        assert_eq!(undone(Action::FireLine(Known(line))), "Put row 4 back to how it was before.");
+        assert_eq!(undone(Action::Unfire(Known(coord))), "Fired at [3, 2].");
+        assert_eq!(undone(Action::Unhit(Known(coord))), "Set hit marker at [3, 2].");
+        assert_eq!(undone(Action::Unsink(Known(3))), "Sunk a ship of length 3.");
+        assert_eq!(undone(Action::UnfireLine(Known(line))), "Fired at every untested coordinate of row 4.");
+    }
+
+    #[test]
+    fn actions_format_as_their_command_and_compare_by_value() {
+        let coord = Coordinate { row: 1, column: 2 };
+        let fire = Action::Fire(Known(coord));
+
+        assert_eq!(fire.to_string(), "fire 3 2");
+        assert_eq!(format!("{fire:?}"), "Action(fire 3 2)");
+        assert_eq!(Action::Sink(Known(4)).to_string(), "sink 4");
+        assert_eq!(Action::Fire(Unknown).to_string(), "fire");
+        assert_eq!(fire, Action::Fire(Known(Coordinate { row: 1, column: 2 })));
+        assert_ne!(fire, Action::Fire(Known(Coordinate { row: 2, column: 1 })));
+        assert_ne!(fire, Action::Hit(Known(coord)));
+    }
+
+    #[test]
+    fn same_variant_ignores_the_arguments() {
+        use strum::IntoEnumIterator;
+
+        let coord = Coordinate { row: 1, column: 2 };
+        assert!(Action::Fire(Known(coord)).same_variant(&Action::Fire(Unknown)));
+        assert!(Action::Sink(Known(2)).same_variant(&Action::Sink(Known(5))));
+        assert!(!Action::Fire(Known(coord)).same_variant(&Action::Unfire(Known(coord))));
+        for action in Action::iter() {
+            for other in Action::iter() {
+                assert_eq!(action.same_variant(&other), std::mem::discriminant(&action) == std::mem::discriminant(&other));
+            }
         }
     }
-}
\ No newline at end of file
+}
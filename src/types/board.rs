@@ -0,0 +1,231 @@
+#[cfg(feature = "rand")]
+use crate::heatmap::placement_cells;
+#[cfg(feature = "rand")]
+use crate::types::{field_ext::FieldExt, rules::Rules};
+use crate::types::{Coordinate, IndexStyle, ShotStatus};
+#[cfg(feature = "rand")]
+use field::helpers::Axis;
+use field::Field;
+use std::fmt;
+
+/*
+* Rust won't let us implement Display on a Field directly (neither the trait nor the type are ours),
+* so this thin wrapper does the job instead. Usage is simply `Board(&shots, style).to_string()`,
+* with the labels counted the way the index style counts.
+* Parsing goes the other way, and accepts both the bare symbols and the labelled Display output.
+*/
+pub struct Board<'a>(pub &'a Field<ShotStatus>, pub IndexStyle);
+
+impl fmt::Display for Board<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shots = self.0;
+        let first_index = self.1.first_index();
+        //wide enough for the largest label on either axis
+        let label_width = (shots.width().max(shots.height()) + first_index)
+            .saturating_sub(1)
+            .to_string()
+            .len();
+
+        write!(f, "{:label_width$}", "")?;
+        for column in (0..shots.width()).map(|column| column + first_index) {
+            write!(f, " {column:>label_width$}")?;
+        }
+        writeln!(f)?;
+
+        for (row_idx, row) in shots.get_grid().iter().enumerate() {
+            write!(f, "{:>label_width$}", row_idx + first_index)?;
+            for status in row {
+                write!(f, " {:>label_width$}", status.symbol())?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+//one row per line. Digits and whitespace are ignored, which takes care of the labels, and lines left empty are skipped.
+pub fn parse_board(text: &str) -> anyhow::Result<Field<ShotStatus>> {
+    let mut grid = vec![];
+    for line in text.lines() {
+        let row = line
+            .chars()
+            .filter(|symbol| !symbol.is_ascii_digit() && !symbol.is_whitespace())
+            .map(|symbol| {
+                ShotStatus::from_symbol(symbol)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown board symbol '{symbol}'."))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if !row.is_empty() {
+            grid.push(row);
+        }
+    }
+
+    let width = grid
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("The board is empty."))?
+        .len();
+    if grid.iter().any(|row| row.len() != width) {
+        return Err(anyhow::anyhow!(
+            "All rows of the board must have the same width."
+        ));
+    }
+
+    let mut shots = Field::new_default(width, grid.len());
+    for (row, line) in grid.into_iter().enumerate() {
+        for (column, status) in line.into_iter().enumerate() {
+            shots.set_value(Coordinate { row, column }, &status)?;
+        }
+    }
+    Ok(shots)
+}
+
+//gives up once this many fresh layouts in a row ran out of room for a ship
+#[cfg(feature = "rand")]
+const MAX_LAYOUT_ATTEMPTS: usize = 100;
+
+//A random truth board for self-play, with every ship cell marked as hit and everything else untested.
+//Ships are placed longest first, horizontally or vertically. If one doesn't fit anymore, the layout starts over.
+#[cfg(feature = "rand")]
+pub fn random_layout(
+    width: usize,
+    height: usize,
+    ships: &[usize],
+    rules: &Rules,
+    rng: &mut impl rand::Rng,
+) -> anyhow::Result<Field<ShotStatus>> {
+    use rand::seq::SliceRandom;
+
+    let mut ships = ships.to_vec();
+    ships.sort_unstable_by(|a, b| b.cmp(a));
+
+    'attempts: for _ in 0..MAX_LAYOUT_ATTEMPTS {
+        let mut layout: Field<ShotStatus> = Field::new_default(width, height);
+        for &ship_length in &ships {
+            let placements = free_placements(&layout, ship_length, rules);
+            let Some(placement) = placements.choose(rng) else {
+                continue 'attempts;
+            };
+            for &coord in placement {
+                layout.set_value(coord, &ShotStatus::Hit)?;
+            }
+        }
+        return Ok(layout);
+    }
+
+    Err(anyhow::anyhow!(
+        "Couldn't fit the fleet on a {width}x{height} board after {MAX_LAYOUT_ATTEMPTS} attempts."
+    ))
+}
+
+#[cfg(feature = "rand")]
+fn free_placements(
+    layout: &Field<ShotStatus>,
+    ship_length: usize,
+    rules: &Rules,
+) -> Vec<Vec<Coordinate>> {
+    let is_free = |coord: Coordinate| {
+        layout.get_grid()[coord.row][coord.column].is_untested()
+            && (!rules.ships_must_not_touch
+                || [layout.neighbors(coord), layout.neighbors_diagonal(coord)]
+                    .concat()
+                    .into_iter()
+                    .all(|neighbor| layout.get_grid()[neighbor.row][neighbor.column].is_untested()))
+    };
+
+    [(ship_length, 1, Axis::Column), (1, ship_length, Axis::Row)]
+        .into_iter()
+        .flat_map(|(width, height, axis)| {
+            layout
+                .windows_2d(width, height)
+                .map(move |(corner, _)| placement_cells(corner, axis, ship_length))
+        })
+        .filter(|placement| placement.iter().all(|&coord| is_free(coord)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::field_ext::FieldExt;
+
+    fn small_board() -> Field<ShotStatus> {
+        let mut shots = Field::new_default(3, 2);
+        shots
+            .set_value(Coordinate { row: 0, column: 1 }, &ShotStatus::Miss)
+            .unwrap();
+        shots
+            .set_value(Coordinate { row: 1, column: 0 }, &ShotStatus::Hit)
+            .unwrap();
+        shots
+            .set_value(Coordinate { row: 1, column: 2 }, &ShotStatus::Sunk)
+            .unwrap();
+        shots
+    }
+
+    #[test]
+    fn renders_symbols_with_labels() {
+        let text = Board(&small_board(), IndexStyle::ChessOneIndexed).to_string();
+        assert_eq!(text, "  1 2 3\n1 . o .\n2 X . #\n");
+    }
+
+    #[test]
+    fn row_major_labels_count_from_zero() {
+        let text = Board(&small_board(), IndexStyle::RowMajorZeroIndexed).to_string();
+        assert_eq!(text, "  0 1 2\n0 . o .\n1 X . #\n");
+    }
+
+    #[test]
+    fn parsing_the_rendering_round_trips() {
+        let shots = small_board();
+        let text = Board(&shots, IndexStyle::ChessOneIndexed).to_string();
+        assert!(parse_board(&text).unwrap().equal(&shots));
+        assert!(parse_board(".o.\nX.#\n").unwrap().equal(&shots));
+    }
+
+    #[test]
+    fn parsing_rejects_ragged_and_unknown_input() {
+        assert!(parse_board(".o.\nX#\n").is_err());
+        assert!(parse_board(".o.\nX?#\n").is_err());
+        assert!(parse_board("\n\n").is_err());
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn random_layouts_fit_the_whole_fleet_without_overlap() {
+        use crate::types::rules::Rules;
+        use rand::SeedableRng;
+
+        let fleet = [2, 3, 3, 4, 5];
+        let no_touch = Rules {
+            ships_must_not_touch: true,
+            ..Rules::classic()
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        for _ in 0..20 {
+            let layout = random_layout(10, 10, &fleet, &Rules::classic(), &mut rng).unwrap();
+            //overlapping ships would share cells and come up short
+            assert_eq!(layout.count_where(ShotStatus::is_hit), fleet.iter().sum());
+
+            //ships that don't touch, not even by a corner, are each a group of their own
+            let layout = random_layout(10, 10, &fleet, &no_touch, &mut rng).unwrap();
+            let mut lengths = layout
+                .connected_components(ShotStatus::is_hit, true)
+                .iter()
+                .map(Vec::len)
+                .collect::<Vec<_>>();
+            lengths.sort_unstable();
+            assert_eq!(lengths, fleet);
+        }
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn random_layout_gives_up_on_a_fleet_that_cannot_fit() {
+        use crate::types::rules::Rules;
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        assert!(random_layout(3, 3, &[4], &Rules::classic(), &mut rng).is_err());
+    }
+}
@@ -0,0 +1,967 @@
+use crate::types::{Coordinate, Step};
+use field::{helpers::Axis, Field};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/*
+* Extra functionality for the field crate's Field, which we can't add to directly.
+*/
+const ORTHOGONAL_OFFSETS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+const DIAGONAL_OFFSETS: [(isize, isize); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+pub trait FieldExt<T> {
+    fn contains_coord(&self, coord: Coordinate) -> bool;
+    fn neighbors(&self, coord: Coordinate) -> Vec<Coordinate>;
+    fn neighbors_diagonal(&self, coord: Coordinate) -> Vec<Coordinate>;
+    fn rotate_90(&self) -> Field<T>
+    where
+        T: Clone + Default;
+    fn flip_horizontal(&self) -> Field<T>
+    where
+        T: Clone + Default;
+    fn flip_vertical(&self) -> Field<T>
+    where
+        T: Clone + Default;
+    fn windows_2d<'a>(
+        &'a self,
+        width: usize,
+        height: usize,
+    ) -> impl Iterator<Item = (Coordinate, Vec<Vec<&'a T>>)>
+    where
+        T: 'a;
+    fn iter_coords<'a>(&'a self) -> impl Iterator<Item = (Coordinate, &'a T)>
+    where
+        T: 'a;
+    fn replace_value(&mut self, coord: Coordinate, value: T) -> anyhow::Result<T>
+    where
+        T: Clone;
+    fn connected_components(
+        &self,
+        predicate: impl Fn(&T) -> bool,
+        diagonal: bool,
+    ) -> Vec<Vec<Coordinate>>;
+    fn equal(&self, other: &Field<T>) -> bool
+    where
+        T: PartialEq;
+    fn diff<'a>(&'a self, other: &'a Field<T>) -> anyhow::Result<Vec<(Coordinate, &'a T, &'a T)>>
+    where
+        T: PartialEq;
+    fn pad(&self, left: usize, right: usize, top: usize, bottom: usize, fill: T) -> Field<T>
+    where
+        T: Clone + Default;
+    fn crop(
+        &self,
+        column: usize,
+        row: usize,
+        width: usize,
+        height: usize,
+    ) -> anyhow::Result<Field<T>>
+    where
+        T: Clone + Default;
+    fn resize(&mut self, width: usize, height: usize, fill: T)
+    where
+        T: Clone + Default;
+    fn resize_strict(&mut self, width: usize, height: usize, fill: T) -> anyhow::Result<()>
+    where
+        T: Clone + Default + PartialEq;
+    fn count_where(&self, predicate: impl Fn(&T) -> bool) -> usize;
+    fn count_by<K: Eq + Hash>(&self, key: impl Fn(&T) -> K) -> HashMap<K, usize>;
+    fn map_lines<R>(&self, axis: Axis, f: impl Fn(&[T]) -> R) -> Vec<R>
+    where
+        T: Clone;
+    fn update_line(
+        &mut self,
+        axis: Axis,
+        index: usize,
+        f: impl Fn(&mut T, usize),
+    ) -> anyhow::Result<()>;
+    fn fold<B>(&self, init: B, f: impl Fn(B, &T) -> B) -> B;
+    fn value_iterator_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut T>
+    where
+        T: 'a;
+    fn transform_all_mut(&mut self, f: impl Fn(&mut T));
+    fn to_flat(&self) -> (Vec<T>, usize, usize)
+    where
+        T: Clone;
+    fn from_flat(data: Vec<T>, width: usize, height: usize) -> anyhow::Result<Field<T>>
+    where
+        T: Clone + Default;
+}
+
+impl<T> FieldExt<T> for Field<T> {
+    fn contains_coord(&self, coord: Coordinate) -> bool {
+        coord.row < self.height() && coord.column < self.width()
+    }
+
+    fn neighbors(&self, coord: Coordinate) -> Vec<Coordinate> {
+        offset_coords(self, coord, &ORTHOGONAL_OFFSETS)
+    }
+
+    fn neighbors_diagonal(&self, coord: Coordinate) -> Vec<Coordinate> {
+        offset_coords(self, coord, &DIAGONAL_OFFSETS)
+    }
+
+    //clockwise, so the left column becomes the top row
+    fn rotate_90(&self) -> Field<T>
+    where
+        T: Clone + Default,
+    {
+        let grid = self.get_grid();
+        from_grid(
+            (0..self.width())
+                .map(|column| grid.iter().rev().map(|line| line[column].clone()).collect())
+                .collect(),
+        )
+    }
+
+    //mirrors left and right
+    fn flip_horizontal(&self) -> Field<T>
+    where
+        T: Clone + Default,
+    {
+        from_grid(
+            self.get_grid()
+                .iter()
+                .map(|line| line.iter().rev().cloned().collect())
+                .collect(),
+        )
+    }
+
+    //mirrors top and bottom
+    fn flip_vertical(&self) -> Field<T>
+    where
+        T: Clone + Default,
+    {
+        from_grid(self.get_grid().iter().rev().cloned().collect())
+    }
+
+    //every width x height section of the field that fits entirely, with its top left corner.
+    //row by row, left to right. Windows that don't fit at all yield nothing.
+    fn windows_2d<'a>(
+        &'a self,
+        width: usize,
+        height: usize,
+    ) -> impl Iterator<Item = (Coordinate, Vec<Vec<&'a T>>)>
+    where
+        T: 'a,
+    {
+        let grid = self.get_grid();
+        let (row_count, column_count) = if width == 0 || height == 0 {
+            (0, 0)
+        } else {
+            (
+                (self.height() + 1).saturating_sub(height),
+                (self.width() + 1).saturating_sub(width),
+            )
+        };
+
+        (0..row_count).flat_map(move |row| {
+            (0..column_count).map(move |column| {
+                let window = grid[row..row + height]
+                    .iter()
+                    .map(|line| line[column..column + width].iter().collect())
+                    .collect();
+                (Coordinate { row, column }, window)
+            })
+        })
+    }
+
+    //every cell with its coordinate, row by row, left to right
+    fn iter_coords<'a>(&'a self) -> impl Iterator<Item = (Coordinate, &'a T)>
+    where
+        T: 'a,
+    {
+        self.get_grid().iter().enumerate().flat_map(|(row, line)| {
+            line.iter()
+                .enumerate()
+                .map(move |(column, value)| (Coordinate { row, column }, value))
+        })
+    }
+
+    //like set_value, but hands back whatever was there before
+    fn replace_value(&mut self, coord: Coordinate, value: T) -> anyhow::Result<T>
+    where
+        T: Clone,
+    {
+        let old_value = self
+            .get_grid()
+            .get(coord.row)
+            .and_then(|line| line.get(coord.column))
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Coordinate is out of bounds."))?;
+        self.set_value(coord, &value)?;
+        Ok(old_value)
+    }
+
+    //groups of adjacent cells matching the predicate. With diagonal, corners count as adjacent too.
+    fn connected_components(
+        &self,
+        predicate: impl Fn(&T) -> bool,
+        diagonal: bool,
+    ) -> Vec<Vec<Coordinate>> {
+        let members = self.find_all(predicate);
+        let mut visited: Vec<Coordinate> = Vec::with_capacity(members.len());
+        let mut components = vec![];
+
+        for &start in &members {
+            if visited.contains(&start) {
+                continue;
+            }
+            visited.push(start);
+
+            //plain flood fill
+            let mut component = vec![];
+            let mut to_visit = vec![start];
+            while let Some(coord) = to_visit.pop() {
+                component.push(coord);
+                let mut neighbors = self.neighbors(coord);
+                if diagonal {
+                    neighbors.extend(self.neighbors_diagonal(coord));
+                }
+                for neighbor in neighbors {
+                    if members.contains(&neighbor) && !visited.contains(&neighbor) {
+                        visited.push(neighbor);
+                        to_visit.push(neighbor);
+                    }
+                }
+            }
+            components.push(component);
+        }
+
+        components
+    }
+
+    //Field itself has no PartialEq, and we can't add one from here
+    fn equal(&self, other: &Field<T>) -> bool
+    where
+        T: PartialEq,
+    {
+        self.get_grid() == other.get_grid()
+    }
+
+    //the cells that differ, with this field's value first. Both fields need the same dimensions.
+    fn diff<'a>(&'a self, other: &'a Field<T>) -> anyhow::Result<Vec<(Coordinate, &'a T, &'a T)>>
+    where
+        T: PartialEq,
+    {
+        if self.width() != other.width() || self.height() != other.height() {
+            return Err(anyhow::anyhow!(
+                "Can't compare a {}x{} field to a {}x{} one.",
+                self.width(),
+                self.height(),
+                other.width(),
+                other.height()
+            ));
+        }
+
+        Ok(self
+            .iter_coords()
+            .zip(other.get_grid().iter().flatten())
+            .filter(|((_, val), other_val)| val != other_val)
+            .map(|((coord, val), other_val)| (coord, val, other_val))
+            .collect())
+    }
+
+    //grows the field by the given number of cells on each side, filled with fill
+    fn pad(&self, left: usize, right: usize, top: usize, bottom: usize, fill: T) -> Field<T>
+    where
+        T: Clone + Default,
+    {
+        let width = left + self.width() + right;
+        let empty_line = vec![fill.clone(); width];
+
+        let mut grid = vec![empty_line.clone(); top];
+        for line in self.get_grid() {
+            let mut padded_line = vec![fill.clone(); left];
+            padded_line.extend(line.iter().cloned());
+            padded_line.extend(vec![fill.clone(); right]);
+            grid.push(padded_line);
+        }
+        grid.extend(vec![empty_line; bottom]);
+
+        from_grid(grid)
+    }
+
+    //the width x height section with its top left corner at the given column and row
+    fn crop(
+        &self,
+        column: usize,
+        row: usize,
+        width: usize,
+        height: usize,
+    ) -> anyhow::Result<Field<T>>
+    where
+        T: Clone + Default,
+    {
+        if column + width > self.width() || row + height > self.height() {
+            return Err(anyhow::anyhow!(
+                "Can't crop a {width}x{height} section at column {column}, row {row} out of a {}x{} field.",
+                self.width(),
+                self.height()
+            ));
+        }
+
+        Ok(from_grid(
+            self.get_grid()[row..row + height]
+                .iter()
+                .map(|line| line[column..column + width].to_vec())
+                .collect(),
+        ))
+    }
+
+    //keeps every cell at its coordinate, growing or shrinking on the right and bottom edges. New cells get fill.
+    fn resize(&mut self, width: usize, height: usize, fill: T)
+    where
+        T: Clone + Default,
+    {
+        let (old_width, old_height) = (self.width(), self.height());
+        *self = self
+            .crop(0, 0, width.min(old_width), height.min(old_height))
+            .unwrap()
+            .pad(
+                0,
+                width.saturating_sub(old_width),
+                0,
+                height.saturating_sub(old_height),
+                fill,
+            );
+    }
+
+    //like resize, but refuses to cut off anything other than default values
+    fn resize_strict(&mut self, width: usize, height: usize, fill: T) -> anyhow::Result<()>
+    where
+        T: Clone + Default + PartialEq,
+    {
+        let dropped_coord = self.iter_coords().find(|(coord, value)| {
+            (coord.row >= height || coord.column >= width) && **value != T::default()
+        });
+        if let Some((coord, _)) = dropped_coord {
+            return Err(anyhow::anyhow!(
+                "Resizing to {width}x{height} would cut off the value at column {}, row {}.",
+                coord.column + 1,
+                coord.row + 1
+            ));
+        }
+        self.resize(width, height, fill);
+        Ok(())
+    }
+
+    fn count_where(&self, predicate: impl Fn(&T) -> bool) -> usize {
+        self.fold(
+            0,
+            |count, val| if predicate(val) { count + 1 } else { count },
+        )
+    }
+
+    //how many cells there are per key. Keys no cell has are left out.
+    fn count_by<K: Eq + Hash>(&self, key: impl Fn(&T) -> K) -> HashMap<K, usize> {
+        let mut counts = HashMap::new();
+        for value in self.get_grid().iter().flatten() {
+            *counts.entry(key(value)).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    //one result per row or column, in order
+    fn map_lines<R>(&self, axis: Axis, f: impl Fn(&[T]) -> R) -> Vec<R>
+    where
+        T: Clone,
+    {
+        (0..self.number_of_lines_in_axis(axis))
+            .map(|index| f(&self.get_line(axis, index).unwrap()))
+            .collect()
+    }
+
+    //changes a row or column where it lies, handing f each cell along with its position in the line.
+    //A column is walked in a single pass down the rows, nothing is copied out and back.
+    fn update_line(
+        &mut self,
+        axis: Axis,
+        index: usize,
+        f: impl Fn(&mut T, usize),
+    ) -> anyhow::Result<()> {
+        if index >= self.number_of_lines_in_axis(axis) {
+            return Err(anyhow::anyhow!("There is no line {index} on this field."));
+        }
+
+        let grid = self.get_grid_mut();
+        match axis {
+            Axis::Row => {
+                for (position, value) in grid[index].iter_mut().enumerate() {
+                    f(value, position);
+                }
+            }
+            Axis::Column => {
+                for (position, line) in grid.iter_mut().enumerate() {
+                    f(&mut line[index], position);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    //row by row, left to right
+    fn fold<B>(&self, init: B, f: impl Fn(B, &T) -> B) -> B {
+        self.get_grid().iter().flatten().fold(init, f)
+    }
+
+    //every cell, row by row, to be changed where it lies
+    fn value_iterator_mut<'a>(&'a mut self) -> impl Iterator<Item = &'a mut T>
+    where
+        T: 'a,
+    {
+        self.get_grid_mut().iter_mut().flatten()
+    }
+
+    //like transform_all when the type stays the same, but changes the field in place instead of building a new one
+    fn transform_all_mut(&mut self, f: impl Fn(&mut T)) {
+        self.value_iterator_mut().for_each(f);
+    }
+
+    //row-major, along with the width and height needed to put it back together
+    fn to_flat(&self) -> (Vec<T>, usize, usize)
+    where
+        T: Clone,
+    {
+        (
+            self.get_grid().iter().flatten().cloned().collect(),
+            self.width(),
+            self.height(),
+        )
+    }
+
+    fn from_flat(data: Vec<T>, width: usize, height: usize) -> anyhow::Result<Field<T>>
+    where
+        T: Clone + Default,
+    {
+        if data.len() != width * height {
+            return Err(anyhow::anyhow!(
+                "Expected {} values for a {width}x{height} field, got {}.",
+                width * height,
+                data.len()
+            ));
+        }
+
+        let mut field = Field::new_default(width, height);
+        for (index, value) in data.into_iter().enumerate() {
+            let coord = Coordinate {
+                row: index / width,
+                column: index % width,
+            };
+            field.set_value(coord, &value)?;
+        }
+        Ok(field)
+    }
+}
+
+//Only makes sense for boolean fields, so it gets a trait of its own
+pub trait Checkerboard {
+    fn checkerboard(width: usize, height: usize, phase: usize) -> Self;
+}
+
+impl Checkerboard for Field<bool> {
+    //true on every phase-th diagonal, so each run of phase cells in a row or column has exactly one.
+    //A phase of 2 is the classic checkerboard.
+    fn checkerboard(width: usize, height: usize, phase: usize) -> Field<bool> {
+        let phase = phase.max(1);
+        let mut field = Field::new_default(width, height);
+        for row in 0..height {
+            for column in 0..width {
+                field
+                    .set_value(Coordinate { row, column }, &((row + column) % phase == 0))
+                    .unwrap();
+            }
+        }
+        field
+    }
+}
+
+//Only makes sense for heat, so it gets a trait of its own as well
+pub trait HeatFieldExt {
+    fn sum(&self) -> f32;
+    fn normalized(&self) -> Self;
+}
+
+impl HeatFieldExt for Field<f32> {
+    fn sum(&self) -> f32 {
+        self.fold(0., |total, val| total + val)
+    }
+
+    //scaled so all values add up to 1. A field without any heat stays all 0.s instead of dividing by 0.
+    fn normalized(&self) -> Field<f32> {
+        let total = self.sum();
+        if total == 0. {
+            return Field::new_default(self.width(), self.height());
+        }
+        self.transform_all(|val| val / total)
+    }
+}
+
+//expects a rectangular grid
+fn from_grid<T: Clone + Default>(grid: Vec<Vec<T>>) -> Field<T> {
+    let width = grid.first().map_or(0, Vec::len);
+    let mut field = Field::new_default(width, grid.len());
+    for (row, line) in grid.into_iter().enumerate() {
+        for (column, value) in line.into_iter().enumerate() {
+            field.set_value(Coordinate { row, column }, &value).unwrap();
+        }
+    }
+    field
+}
+
+fn offset_coords<T>(
+    field: &Field<T>,
+    coord: Coordinate,
+    offsets: &[(isize, isize)],
+) -> Vec<Coordinate> {
+    offsets
+        .iter()
+        .filter_map(|&(row_offset, column_offset)| coord.offset(column_offset, row_offset))
+        .filter(|&neighbor| field.contains_coord(neighbor))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ShotStatus;
+
+    fn coord(row: usize, column: usize) -> Coordinate {
+        Coordinate { row, column }
+    }
+
+    //as (row, column) pairs in row-major order, so they can be compared and printed
+    fn sorted(coords: Vec<Coordinate>) -> Vec<(usize, usize)> {
+        let mut pairs = coords
+            .into_iter()
+            .map(|coord| (coord.row, coord.column))
+            .collect::<Vec<_>>();
+        pairs.sort_unstable();
+        pairs
+    }
+
+    #[test]
+    fn neighbors_stay_on_the_board() {
+        let field: Field<u8> = Field::new_default(4, 3);
+
+        assert_eq!(sorted(field.neighbors(coord(0, 0))), [(0, 1), (1, 0)]);
+        assert_eq!(
+            sorted(field.neighbors(coord(0, 2))),
+            [(0, 1), (0, 3), (1, 2)]
+        );
+        assert_eq!(
+            sorted(field.neighbors(coord(1, 1))),
+            [(0, 1), (1, 0), (1, 2), (2, 1)]
+        );
+        assert_eq!(sorted(field.neighbors(coord(2, 3))), [(1, 3), (2, 2)]);
+    }
+
+    #[test]
+    fn diagonal_neighbors_stay_on_the_board() {
+        let field: Field<u8> = Field::new_default(4, 3);
+
+        assert_eq!(sorted(field.neighbors_diagonal(coord(0, 0))), [(1, 1)]);
+        assert_eq!(
+            sorted(field.neighbors_diagonal(coord(2, 1))),
+            [(1, 0), (1, 2)]
+        );
+        assert_eq!(
+            sorted(field.neighbors_diagonal(coord(1, 1))),
+            [(0, 0), (0, 2), (2, 0), (2, 2)]
+        );
+    }
+
+    //1 2 3
+    //4 5 6
+    fn two_by_three() -> Field<u8> {
+        Field::from_flat(vec![1, 2, 3, 4, 5, 6], 3, 2).unwrap()
+    }
+
+    #[test]
+    fn rotating_swaps_the_dimensions_and_four_turns_are_a_full_circle() {
+        let field = two_by_three();
+        let rotated = field.rotate_90();
+        assert_eq!((rotated.width(), rotated.height()), (2, 3));
+        assert_eq!(
+            rotated.get_grid(),
+            &vec![vec![4, 1], vec![5, 2], vec![6, 3]]
+        );
+
+        let full_circle = rotated.rotate_90().rotate_90().rotate_90();
+        assert!(full_circle.equal(&field));
+    }
+
+    #[test]
+    fn flips_mirror_the_field() {
+        let field = two_by_three();
+        assert_eq!(
+            field.flip_horizontal().get_grid()[..],
+            [vec![3, 2, 1], vec![6, 5, 4]]
+        );
+        assert_eq!(
+            field.flip_vertical().get_grid()[..],
+            [vec![4, 5, 6], vec![1, 2, 3]]
+        );
+        assert!(field.flip_horizontal().flip_horizontal().equal(&field));
+    }
+
+    #[test]
+    fn count_where_and_fold_visit_every_cell() {
+        let field = Field::from_flat(vec![1, -2, 3, 0, 5, -6], 3, 2).unwrap();
+
+        assert_eq!(field.count_where(|&val| val > 0), 3);
+        assert_eq!(field.count_where(|&val| val > 10), 0);
+        assert_eq!(field.fold(0, |sum, &val| sum + val), 1);
+        //row by row, left to right
+        assert_eq!(
+            field.fold(String::new(), |text, val| text + &val.to_string()),
+            "1-2305-6"
+        );
+    }
+
+    #[test]
+    fn windows_cover_every_position_that_fits() {
+        let field = two_by_three();
+        assert_eq!(field.windows_2d(1, 1).count(), 6);
+        assert_eq!(field.windows_2d(2, 1).count(), 4);
+        assert_eq!(field.windows_2d(2, 2).count(), 2);
+        assert_eq!(field.windows_2d(3, 2).count(), 1);
+        assert_eq!(field.windows_2d(4, 1).count(), 0);
+        assert_eq!(field.windows_2d(1, 3).count(), 0);
+
+        let (corner, window) = field.windows_2d(2, 2).last().unwrap();
+        assert_eq!((corner.row, corner.column), (0, 1));
+        assert_eq!(window, [[&2, &3], [&5, &6]]);
+    }
+
+    #[test]
+    fn replace_value_hands_back_the_displaced_status() {
+        let mut field: Field<ShotStatus> = Field::new_default(3, 2);
+        assert_eq!(
+            field.replace_value(coord(1, 2), ShotStatus::Hit).unwrap(),
+            ShotStatus::Untested
+        );
+        assert_eq!(
+            field.replace_value(coord(1, 2), ShotStatus::Sunk).unwrap(),
+            ShotStatus::Hit
+        );
+        assert_eq!(field.get_grid()[1][2], ShotStatus::Sunk);
+    }
+
+    #[test]
+    fn replace_value_out_of_bounds_changes_nothing() {
+        let mut field: Field<ShotStatus> = Field::new_default(3, 2);
+        assert!(field.replace_value(coord(2, 0), ShotStatus::Hit).is_err());
+        assert!(field.replace_value(coord(0, 3), ShotStatus::Hit).is_err());
+        assert!(field.equal(&Field::new_default(3, 2)));
+    }
+
+    //two separate pairs of hits and an L, where the lone hit at the bottom only touches a pair by its corner
+    //x x . . x
+    //. . . . x
+    //x . . x x
+    //x x . . .
+    //. . x . .
+    fn hit_clusters() -> Field<ShotStatus> {
+        let mut field: Field<ShotStatus> = Field::new_default(5, 5);
+        for (row, column) in [
+            (0, 0),
+            (0, 1),
+            (0, 4),
+            (1, 4),
+            (2, 0),
+            (2, 3),
+            (2, 4),
+            (3, 0),
+            (3, 1),
+            (4, 2),
+        ] {
+            field
+                .set_value(coord(row, column), &ShotStatus::Hit)
+                .unwrap();
+        }
+        field
+    }
+
+    fn sorted_components(components: Vec<Vec<Coordinate>>) -> Vec<Vec<(usize, usize)>> {
+        let mut components = components.into_iter().map(sorted).collect::<Vec<_>>();
+        components.sort_unstable();
+        components
+    }
+
+    #[test]
+    fn orthogonal_components_keep_corner_touches_apart() {
+        let components = hit_clusters().connected_components(ShotStatus::is_hit, false);
+        assert_eq!(
+            sorted_components(components),
+            [
+                vec![(0, 0), (0, 1)],
+                vec![(0, 4), (1, 4), (2, 3), (2, 4)],
+                vec![(2, 0), (3, 0), (3, 1)],
+                vec![(4, 2)],
+            ]
+        );
+    }
+
+    #[test]
+    fn diagonal_components_join_across_corners() {
+        let components = hit_clusters().connected_components(ShotStatus::is_hit, true);
+        assert_eq!(
+            sorted_components(components),
+            [
+                vec![(0, 0), (0, 1)],
+                vec![(0, 4), (1, 4), (2, 3), (2, 4)],
+                vec![(2, 0), (3, 0), (3, 1), (4, 2)],
+            ]
+        );
+    }
+
+    #[test]
+    fn no_matching_cells_means_no_components() {
+        let field: Field<ShotStatus> = Field::new_default(3, 3);
+        assert!(field
+            .connected_components(ShotStatus::is_hit, true)
+            .is_empty());
+    }
+
+    #[test]
+    fn iter_coords_visits_every_cell_once_in_row_major_order() {
+        let field = two_by_three();
+        let cells = field
+            .iter_coords()
+            .map(|(coord, &value)| (coord.row, coord.column, value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cells,
+            [
+                (0, 0, 1),
+                (0, 1, 2),
+                (0, 2, 3),
+                (1, 0, 4),
+                (1, 1, 5),
+                (1, 2, 6)
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_fields_are_equal_with_an_empty_diff() {
+        let (field, copy) = (two_by_three(), two_by_three());
+        assert!(field.equal(&copy));
+        assert!(field.diff(&copy).unwrap().is_empty());
+    }
+
+    #[test]
+    fn diff_lists_the_cells_that_differ() {
+        let field = two_by_three();
+        let other = Field::from_flat(vec![1, 9, 3, 4, 5, 0], 3, 2).unwrap();
+        assert!(!field.equal(&other));
+
+        let differences = field
+            .diff(&other)
+            .unwrap()
+            .into_iter()
+            .map(|(coord, &val, &other_val)| (coord.row, coord.column, val, other_val))
+            .collect::<Vec<_>>();
+        assert_eq!(differences, [(0, 1, 2, 9), (1, 2, 6, 0)]);
+    }
+
+    #[test]
+    fn diff_needs_matching_dimensions() {
+        let field = two_by_three();
+        assert!(field.diff(&field.rotate_90()).is_err());
+        assert!(!field.equal(&field.rotate_90()));
+    }
+
+    #[test]
+    fn padding_an_empty_board_grows_it_with_the_fill() {
+        let field: Field<ShotStatus> = Field::new_default(2, 2);
+        let padded = field.pad(1, 2, 0, 1, ShotStatus::Miss);
+        assert_eq!((padded.width(), padded.height()), (5, 3));
+        assert_eq!(padded.count_where(ShotStatus::is_untested), 4);
+        assert_eq!(padded.count_where(ShotStatus::is_miss), 11);
+        assert!(padded.get_grid()[0][1..3] == [ShotStatus::Untested; 2]);
+        assert!(padded.get_grid()[2] == [ShotStatus::Miss; 5]);
+    }
+
+    #[test]
+    fn cropping_keeps_the_interior_hits_in_place() {
+        let mut field: Field<ShotStatus> = Field::new_default(4, 4);
+        field.set_value(coord(1, 1), &ShotStatus::Hit).unwrap();
+        field.set_value(coord(2, 2), &ShotStatus::Sunk).unwrap();
+
+        let cropped = field.crop(1, 1, 2, 2).unwrap();
+        assert_eq!(
+            cropped.get_grid()[..],
+            [
+                vec![ShotStatus::Hit, ShotStatus::Untested],
+                vec![ShotStatus::Untested, ShotStatus::Sunk]
+            ]
+        );
+        assert!(field.crop(3, 1, 2, 2).is_err());
+        assert!(field.crop(0, 0, 4, 5).is_err());
+    }
+
+    #[test]
+    fn flat_round_trip_keeps_a_non_square_field() {
+        let field = two_by_three();
+        let (data, width, height) = field.to_flat();
+        assert_eq!(
+            (data.clone(), width, height),
+            (vec![1, 2, 3, 4, 5, 6], 3, 2)
+        );
+        assert!(Field::from_flat(data, width, height).unwrap().equal(&field));
+    }
+
+    #[test]
+    fn from_flat_rejects_data_of_the_wrong_length() {
+        assert!(Field::from_flat(vec![1u8; 5], 3, 2).is_err());
+        assert!(Field::from_flat(vec![1u8; 7], 3, 2).is_err());
+    }
+
+    #[test]
+    fn classic_checkerboard_covers_half_the_board() {
+        let mask = Field::<bool>::checkerboard(8, 8, 2);
+        assert_eq!(mask.count_where(|&marked| marked), 32);
+        assert!(mask.get_grid()[0][0] && !mask.get_grid()[0][1] && mask.get_grid()[1][1]);
+    }
+
+    #[test]
+    fn checkerboard_marks_one_cell_in_every_run_of_phase_cells() {
+        //phase 3, as for a fleet whose shortest ship is 3 long
+        let mask = Field::<bool>::checkerboard(7, 5, 3);
+        for line in mask.get_grid() {
+            assert!(line
+                .windows(3)
+                .all(|run| run.iter().filter(|&&marked| marked).count() == 1));
+        }
+        for column in 0..7 {
+            let line = mask.get_line(Axis::Column, column).unwrap();
+            assert!(line
+                .windows(3)
+                .all(|run| run.iter().filter(|&&marked| marked).count() == 1));
+        }
+    }
+
+    #[test]
+    fn transform_all_mut_agrees_with_transform_all() {
+        let field = two_by_three();
+        let transformed = field.transform_all(|&val| val * 3 + 1);
+        let mut transformed_in_place = field.clone();
+        transformed_in_place.transform_all_mut(|val| *val = *val * 3 + 1);
+        assert!(transformed_in_place.equal(&transformed));
+        assert_eq!(transformed.get_grid()[1][..], [13, 16, 19]);
+    }
+
+    #[test]
+    fn value_iterator_mut_walks_the_rows_in_order() {
+        let mut field = two_by_three();
+        for (index, val) in field.value_iterator_mut().enumerate() {
+            *val *= index as u8;
+        }
+        assert_eq!(field.to_flat(), (vec![0, 2, 6, 12, 20, 30], 3, 2));
+    }
+
+    #[test]
+    fn heat_sums_and_normalizes() {
+        let heat = Field::from_flat(vec![0.5, 0.25, 0., 1., 0.75, 0.5], 3, 2).unwrap();
+        assert!((heat.sum() - 3.).abs() < 1e-6);
+
+        let normalized = heat.normalized();
+        assert!((normalized.sum() - 1.).abs() < 1e-6);
+        assert!((normalized.get_grid()[1][0] - 1. / 3.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn normalizing_cold_heat_stays_cold() {
+        let heat: Field<f32> = Field::new_default(3, 2);
+        assert!(heat.normalized().equal(&heat));
+    }
+
+    #[test]
+    fn map_lines_counts_per_row_and_column() {
+        //x . x x
+        //. . . .
+        //x x . x
+        let field = Field::from_flat(
+            vec![
+                true, false, true, true, false, false, false, false, true, true, false, true,
+            ],
+            4,
+            3,
+        )
+        .unwrap();
+        let count_true = |line: &[bool]| line.iter().filter(|&&val| val).count();
+
+        assert_eq!(field.map_lines(Axis::Row, count_true), [3, 0, 3]);
+        assert_eq!(field.map_lines(Axis::Column, count_true), [2, 1, 1, 2]);
+    }
+
+    #[test]
+    fn growing_keeps_a_hit_in_the_corner() {
+        let mut field: Field<ShotStatus> = Field::new_default(3, 2);
+        field.set_value(coord(0, 0), &ShotStatus::Hit).unwrap();
+
+        field.resize(5, 4, ShotStatus::Untested);
+        assert_eq!((field.width(), field.height()), (5, 4));
+        assert_eq!(field.get_grid()[0][0], ShotStatus::Hit);
+        assert_eq!(field.count_where(ShotStatus::is_untested), 19);
+    }
+
+    #[test]
+    fn strict_shrinking_refuses_to_drop_a_hit() {
+        let mut field: Field<ShotStatus> = Field::new_default(4, 4);
+        field.set_value(coord(2, 1), &ShotStatus::Hit).unwrap();
+
+        assert!(field.resize_strict(4, 2, ShotStatus::Untested).is_err());
+        assert_eq!((field.width(), field.height()), (4, 4));
+        field.resize_strict(2, 3, ShotStatus::Untested).unwrap();
+        assert_eq!((field.width(), field.height()), (2, 3));
+        assert_eq!(field.get_grid()[2][1], ShotStatus::Hit);
+    }
+
+    #[test]
+    fn count_by_gives_the_histogram_of_a_board() {
+        let mut field: Field<ShotStatus> = Field::new_default(3, 2);
+        let empty_counts = field.count_by(|&status| status);
+        assert_eq!(empty_counts, HashMap::from([(ShotStatus::Untested, 6)]));
+
+        field.set_value(coord(0, 0), &ShotStatus::Miss).unwrap();
+        field.set_value(coord(0, 1), &ShotStatus::Miss).unwrap();
+        field.set_value(coord(1, 1), &ShotStatus::Hit).unwrap();
+        field.set_value(coord(1, 2), &ShotStatus::Sunk).unwrap();
+        assert_eq!(
+            field.count_by(|&status| status),
+            HashMap::from([
+                (ShotStatus::Untested, 2),
+                (ShotStatus::Miss, 2),
+                (ShotStatus::Hit, 1),
+                (ShotStatus::Sunk, 1)
+            ])
+        );
+    }
+
+    #[test]
+    fn adding_in_place_matches_merging_whole_lines() {
+        let row = [0.25f32, 0.5, 0.125, 1.];
+        let column = [0.5f32, 0.75, 0.];
+        let mut merged = Field::from_flat((0..12).map(|val| val as f32).collect(), 4, 3).unwrap();
+        let mut updated = merged.clone();
+
+        merged
+            .merge_line(Axis::Row, 1, &row, |acc, e| acc + e)
+            .unwrap();
+        merged
+            .merge_line(Axis::Column, 2, &column, |acc, e| acc + e)
+            .unwrap();
+        updated
+            .update_line(Axis::Row, 1, |val, position| *val += row[position])
+            .unwrap();
+        updated
+            .update_line(Axis::Column, 2, |val, position| *val += column[position])
+            .unwrap();
+
+        assert!(updated.equal(&merged));
+        assert!(updated.update_line(Axis::Column, 4, |_, _| ()).is_err());
+    }
+}
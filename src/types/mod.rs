@@ -1,12 +1,18 @@
 pub mod action;
+pub mod board;
+pub mod field_ext;
+pub mod query;
+pub mod rules;
+pub mod ship_shape;
 pub use field::helpers::Coordinate;
+use field::helpers::Axis;
 /*
 * A collection of tiny helper enums used
 * by multiple parts of the system.
 * As they don't really make sense to associate
 * with any other module, they're collected here.
 */
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash)]
 pub enum ShotStatus {
     #[default]
     Untested,
@@ -22,23 +28,335 @@ impl ShotStatus {
             ShotStatus::Miss | ShotStatus::Sunk => false,
         }
     }
+
+    pub fn is_untested(&self) -> bool {
+        *self == ShotStatus::Untested
+    }
+
+    pub fn is_miss(&self) -> bool {
+        *self == ShotStatus::Miss
+    }
+
+    pub fn is_hit(&self) -> bool {
+        *self == ShotStatus::Hit
+    }
+
+    pub fn is_sunk(&self) -> bool {
+        *self == ShotStatus::Sunk
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            ShotStatus::Untested => "untested",
+            ShotStatus::Miss => "miss",
+            ShotStatus::Hit => "hit",
+            ShotStatus::Sunk => "sunk",
+        }
+    }
+
+    pub fn from_symbol(symbol: char) -> Option<ShotStatus> {
+        match symbol {
+            '.' => Some(ShotStatus::Untested),
+            'o' => Some(ShotStatus::Miss),
+            'X' => Some(ShotStatus::Hit),
+            '#' => Some(ShotStatus::Sunk),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(&self) -> char {
+        match self {
+            ShotStatus::Untested => '.',
+            ShotStatus::Miss => 'o',
+            ShotStatus::Hit => 'X',
+            ShotStatus::Sunk => '#',
+        }
+    }
 }
 
 pub trait Printable {
-    fn printable(&self) -> String;
-    fn from_user(column: usize, row: usize) -> Coordinate;
+    fn printable(&self, style: IndexStyle) -> String;
+    fn to_user(&self, style: IndexStyle) -> (usize, usize);
+    fn from_user(first: usize, second: usize, style: IndexStyle) -> Coordinate;
+    fn from_user_checked(
+        first: usize,
+        second: usize,
+        width: usize,
+        height: usize,
+        style: IndexStyle,
+    ) -> anyhow::Result<Coordinate>;
+    fn from_chess(text: &str, width: usize, height: usize) -> anyhow::Result<Coordinate>;
+}
+
+//How coordinates are typed and shown. Part of the rules, so it's picked once and handed to whatever prints or parses.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum IndexStyle {
+    //'column row', counting from 1
+    #[default]
+    ChessOneIndexed,
+    //'row column', counting from 0
+    RowMajorZeroIndexed,
+}
+
+impl IndexStyle {
+    pub const NAMES: [&'static str; 2] = ["chess", "row-major"];
+
+    pub fn from_name(name: &str) -> Option<IndexStyle> {
+        match name {
+            "chess" => Some(IndexStyle::ChessOneIndexed),
+            "row-major" => Some(IndexStyle::RowMajorZeroIndexed),
+            _ => None,
+        }
+    }
+
+    //what the first row or column is called
+    pub fn first_index(&self) -> usize {
+        match self {
+            IndexStyle::ChessOneIndexed => 1,
+            IndexStyle::RowMajorZeroIndexed => 0,
+        }
+    }
+
+    //the arguments of a coordinate, for the help texts
+    pub fn tx_coordinate_args(&self) -> &'static str {
+        match self {
+            IndexStyle::ChessOneIndexed => "<column> <row>",
+            IndexStyle::RowMajorZeroIndexed => "<row> <column>",
+        }
+    }
+
+    //how a coordinate is counted, for the help texts. Chess notation works in either style.
+    pub fn tx_coordinate_hint(&self) -> &'static str {
+        match self {
+            IndexStyle::ChessOneIndexed => "[1-index, column may be a letter like 'E3']",
+            IndexStyle::RowMajorZeroIndexed => "[0-index, or chess notation like 'E3']",
+        }
+    }
+
+    //how a single row or column is counted, for the help texts
+    pub fn tx_index_hint(&self) -> &'static str {
+        match self {
+            IndexStyle::ChessOneIndexed => "[1-index]",
+            IndexStyle::RowMajorZeroIndexed => "[0-index]",
+        }
+    }
 }
 
+//The pairs of numbers are in the order the user types them, see IndexStyle
 impl Printable for Coordinate {
     // This is very silly, but I simply do not think in row-major or zero-index
     // when playing grid-based games. Blame chess I guess.
-    fn printable(&self) -> String {
-        format!("[{}, {}]", self.column + 1, self.row + 1)
+    fn printable(&self, style: IndexStyle) -> String {
+        let (first, second) = self.to_user(style);
+        match style {
+            IndexStyle::ChessOneIndexed => format!("[{first}, {second}]"),
+            IndexStyle::RowMajorZeroIndexed => format!("({first}, {second})"),
+        }
+    }
+    fn to_user(&self, style: IndexStyle) -> (usize, usize) {
+        match style {
+            IndexStyle::ChessOneIndexed => (self.column + 1, self.row + 1),
+            IndexStyle::RowMajorZeroIndexed => (self.row, self.column),
+        }
     }
-    fn from_user(column: usize, row: usize) -> Coordinate {
-        Coordinate {
+    fn from_user(first: usize, second: usize, style: IndexStyle) -> Coordinate {
+        match style {
+            IndexStyle::ChessOneIndexed => Coordinate {
+                row: second - 1,
+                column: first - 1,
+            },
+            IndexStyle::RowMajorZeroIndexed => Coordinate {
+                row: first,
+                column: second,
+            },
+        }
+    }
+    fn from_user_checked(
+        first: usize,
+        second: usize,
+        width: usize,
+        height: usize,
+        style: IndexStyle,
+    ) -> anyhow::Result<Coordinate> {
+        let (column, row) = match style {
+            IndexStyle::ChessOneIndexed => (first, second),
+            IndexStyle::RowMajorZeroIndexed => (second, first),
+        };
+        let first_index = style.first_index();
+        let last_index = |line_count: usize| line_count + first_index - 1;
+        if !(first_index..=last_index(width)).contains(&column) {
+            return Err(anyhow::anyhow!(
+                "Column must be between {first_index} and {}.",
+                last_index(width)
+            ));
+        }
+        if !(first_index..=last_index(height)).contains(&row) {
+            return Err(anyhow::anyhow!(
+                "Row must be between {first_index} and {}.",
+                last_index(height)
+            ));
+        }
+        Ok(Coordinate::from_user(first, second, style))
+    }
+    //"E3" is column 5, row 3, just like on a chess board. Whatever the index style, this stays chess.
+    fn from_chess(text: &str, width: usize, height: usize) -> anyhow::Result<Coordinate> {
+        let mut chars = text.chars();
+        let letter = chars
+            .next()
+            .filter(char::is_ascii_alphabetic)
+            .ok_or_else(|| anyhow::anyhow!("Expected a column letter followed by a row number."))?;
+        let column = (letter.to_ascii_uppercase() as u8 - b'A') as usize;
+        let row = chars
+            .as_str()
+            .parse::<usize>()
+            .map_err(|_| anyhow::anyhow!("Unable to read given numeric value."))?;
+
+        if column >= width {
+            return Err(anyhow::anyhow!("Column '{letter}' is outside the board."));
+        }
+        if !(1..=height).contains(&row) {
+            return Err(anyhow::anyhow!("Row must be between 1 and {height}."));
+        }
+        Ok(Coordinate {
             row: row - 1,
-            column: column - 1,
+            column,
+        })
+    }
+}
+
+//Axis belongs to the field crate, so listing its variants needs a trait of our own
+pub trait AxisExt {
+    fn all() -> [Axis; 2];
+}
+
+impl AxisExt for Axis {
+    fn all() -> [Axis; 2] {
+        [Axis::Row, Axis::Column]
+    }
+}
+
+//Moving around the board. Steps off the top or left edge give None, the other edges are up to the caller.
+pub trait Step {
+    //moves the index of the given axis, so Axis::Row changes the row
+    fn step(&self, axis: Axis, delta: isize) -> Option<Coordinate>;
+    fn offset(&self, column_delta: isize, row_delta: isize) -> Option<Coordinate>;
+}
+
+impl Step for Coordinate {
+    fn step(&self, axis: Axis, delta: isize) -> Option<Coordinate> {
+        let mut coord = *self;
+        coord.set_axis_index(axis, self.get_axis_index(axis).checked_add_signed(delta)?);
+        Some(coord)
+    }
+    fn offset(&self, column_delta: isize, row_delta: isize) -> Option<Coordinate> {
+        Some(Coordinate {
+            row: self.row.checked_add_signed(row_delta)?,
+            column: self.column.checked_add_signed(column_delta)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //as (row, column), since Coordinate can't be printed by assert_eq
+    fn checked(first: usize, second: usize, style: IndexStyle) -> anyhow::Result<(usize, usize)> {
+        Coordinate::from_user_checked(first, second, 9, 7, style).map(|coord| (coord.row, coord.column))
+    }
+
+    #[test]
+    fn checked_coordinates_reject_zero_and_over_range() {
+        assert_eq!(checked(9, 7, IndexStyle::ChessOneIndexed).unwrap(), (6, 8));
+        assert!(checked(0, 3, IndexStyle::ChessOneIndexed).is_err());
+        assert!(checked(3, 0, IndexStyle::ChessOneIndexed).is_err());
+        assert!(checked(10, 3, IndexStyle::ChessOneIndexed).is_err());
+        assert!(checked(3, 8, IndexStyle::ChessOneIndexed).is_err());
+    }
+
+    fn chess(text: &str) -> anyhow::Result<(usize, usize)> {
+        Coordinate::from_chess(text, 9, 7).map(|coord| (coord.row, coord.column))
+    }
+
+    #[test]
+    fn chess_notation_maps_letters_to_columns() {
+        assert_eq!(chess("a1").unwrap(), (0, 0));
+        assert_eq!(chess("A1").unwrap(), (0, 0));
+        assert_eq!(chess("E3").unwrap(), (2, 4));
+        assert_eq!(chess("e3").unwrap(), (2, 4));
+        assert_eq!(chess("I7").unwrap(), (6, 8));
+        //the board is 9 wide, so 'I' is the last column
+        assert!(chess("J1").is_err());
+        assert!(chess("A8").is_err());
+        assert!(chess("3E").is_err());
+    }
+
+    #[test]
+    fn status_predicates_match_the_variants() {
+        for status in [ShotStatus::Untested, ShotStatus::Miss, ShotStatus::Hit, ShotStatus::Sunk] {
+            let expected = match status {
+                ShotStatus::Untested => [true, false, false, false],
+                ShotStatus::Miss => [false, true, false, false],
+                ShotStatus::Hit => [false, false, true, false],
+                ShotStatus::Sunk => [false, false, false, true],
+            };
+            assert_eq!([status.is_untested(), status.is_miss(), status.is_hit(), status.is_sunk()], expected);
         }
     }
+
+    fn pair(coord: Option<Coordinate>) -> Option<(usize, usize)> {
+        coord.map(|coord| (coord.row, coord.column))
+    }
+
+    #[test]
+    fn stepping_off_the_top_left_gives_none() {
+        let corner = Coordinate { row: 0, column: 0 };
+        assert_eq!(pair(corner.step(Axis::Row, -1)), None);
+        assert_eq!(pair(corner.step(Axis::Column, -1)), None);
+        assert_eq!(pair(corner.offset(-1, 0)), None);
+        assert_eq!(pair(corner.offset(0, -1)), None);
+        assert_eq!(pair(corner.offset(1, -1)), None);
+    }
+
+    #[test]
+    fn stepping_moves_along_the_given_axis() {
+        let coord = Coordinate { row: 2, column: 3 };
+        assert_eq!(pair(coord.step(Axis::Row, 1)), Some((3, 3)));
+        assert_eq!(pair(coord.step(Axis::Row, -2)), Some((0, 3)));
+        assert_eq!(pair(coord.step(Axis::Column, 4)), Some((2, 7)));
+        assert_eq!(pair(coord.step(Axis::Column, -3)), Some((2, 0)));
+        assert_eq!(pair(coord.offset(-1, 2)), Some((4, 2)));
+        assert_eq!(pair(coord.offset(0, 0)), Some((2, 3)));
+    }
+
+    #[test]
+    fn opposite_axes_swap_rows_and_columns() {
+        assert!(matches!(Axis::Row.opposite(), Axis::Column));
+        assert!(matches!(Axis::Column.opposite(), Axis::Row));
+        assert!(matches!(Axis::Row.opposite().opposite(), Axis::Row));
+    }
+
+    #[test]
+    fn all_axes_are_rows_then_columns() {
+        assert!(matches!(Axis::all(), [Axis::Row, Axis::Column]));
+    }
+
+    #[test]
+    fn each_style_parses_and_prints_the_same_cell_its_own_way() {
+        let cell = Coordinate { row: 2, column: 4 };
+        for (style, typed, printed) in [(IndexStyle::ChessOneIndexed, (5, 3), "[5, 3]"), (IndexStyle::RowMajorZeroIndexed, (2, 4), "(2, 4)")] {
+            assert!(Coordinate::from_user(typed.0, typed.1, style) == cell);
+            assert_eq!(checked(typed.0, typed.1, style).unwrap(), (2, 4));
+            assert_eq!(cell.to_user(style), typed);
+            assert_eq!(cell.printable(style), printed);
+        }
+    }
+
+    #[test]
+    fn row_major_coordinates_count_from_zero() {
+        assert_eq!(checked(0, 0, IndexStyle::RowMajorZeroIndexed).unwrap(), (0, 0));
+        assert_eq!(checked(6, 8, IndexStyle::RowMajorZeroIndexed).unwrap(), (6, 8));
+        assert!(checked(7, 0, IndexStyle::RowMajorZeroIndexed).is_err());
+        assert!(checked(0, 9, IndexStyle::RowMajorZeroIndexed).is_err());
+    }
 }
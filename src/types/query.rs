@@ -0,0 +1,112 @@
+use crate::types::IndexStyle;
+use strum_macros::EnumIter;
+
+/*
+* Queries are commands that don't act on the board.
+* Unlike actions they never touch the shots or the action history,
+* so they don't need arguments that can be inferred or undone.
+* The exceptions are 'resize', which reshapes the whole board and therefore starts a fresh history,
+* and 'goto', which moves back and forth through the history.
+*/
+#[derive(EnumIter, Clone, Copy)]
+pub enum Query {
+    Prob,
+    Top,
+    Parity,
+    Salvo,
+    Heat,
+    Stats,
+    Explain,
+    Rules,
+    Fleet,
+    Diff,
+    History,
+    SaveHistory,
+    Percent,
+    Gradient,
+    Resize,
+    Simulate,
+    Mask,
+    Placements,
+    Density,
+    Goto,
+    #[cfg(feature = "image")]
+    Render,
+}
+
+impl Query {
+    pub fn name(&self) -> &str {
+        match *self {
+            Query::Prob => "prob",
+            Query::Top => "top",
+            Query::Parity => "parity",
+            Query::Salvo => "salvo",
+            Query::Heat => "heat",
+            Query::Stats => "stats",
+            Query::Explain => "explain",
+            Query::Rules => "rules",
+            Query::Fleet => "fleet",
+            Query::Diff => "diff",
+            Query::History => "history",
+            Query::SaveHistory => "save-history",
+            Query::Percent => "percent",
+            Query::Gradient => "gradient",
+            Query::Resize => "resize",
+            Query::Simulate => "simulate",
+            Query::Mask => "mask",
+            Query::Placements => "placements",
+            Query::Density => "density",
+            Query::Goto => "goto",
+            #[cfg(feature = "image")]
+            Query::Render => "render",
+        }
+    }
+
+    pub fn expected_arg_count(&self) -> usize {
+        match *self {
+            Query::Simulate => 3,
+            Query::Prob | Query::Heat | Query::Explain | Query::Rules | Query::Resize => 2,
+            Query::Top
+            | Query::Parity
+            | Query::Diff
+            | Query::SaveHistory
+            | Query::Percent
+            | Query::Gradient
+            | Query::Mask
+            | Query::Placements
+            | Query::Density
+            | Query::Goto => 1,
+            #[cfg(feature = "image")]
+            Query::Render => 1,
+            Query::Salvo | Query::Stats | Query::Fleet | Query::History => 0,
+        }
+    }
+
+    pub fn tx_syntax_help(&self, style: IndexStyle) -> String {
+        let (args, hint) = (style.tx_coordinate_args(), style.tx_index_hint());
+        match self {
+            Query::Prob => format!("'prob {args}' {hint} Shows the computed probability of a ship at the specified coordinate."),
+            Query::Top => "'top <n>' Lists the n most likely untested coordinates, best first.".to_owned(),
+            Query::Parity => "'parity <on|off>' While no ship is hit, only recommends coordinates on a grid spaced by the smallest remaining ship.\n\t'parity show' Marks the untested coordinates on that grid with '+'.".to_owned(),
+            Query::Salvo => "'salvo' Recommends one shot per remaining ship, for games played with salvo rules.".to_owned(),
+            Query::Heat => "'heat export-csv <path>' Writes the current probabilities to a CSV file, one board row per line.".to_owned(),
+            Query::Stats => "'stats' Shows shots fired, hit rate and ships sunk so far.".to_owned(),
+            Query::Explain => format!("'explain {args}' {hint} Shows how many placements of each ship cover the coordinate, whether a nearby hit boosts it, and the heat of each layer."),
+            Query::Rules => "'rules <no-touch|diagonal|parity|salvo|lenient-sink|center|info-gain> <on|off>' Switches a house rule on or off.\n\t'rules combine <product|max|sum>' Picks how the heat of the different ship lengths is combined.".to_owned(),
+            Query::Fleet => "'fleet' Lists the ships that are still afloat, grouped by length.".to_owned(),
+            Query::Diff => "'diff <path>' Lists the coordinates where the current board differs from the one saved in the file.".to_owned(),
+            Query::History => "'history' Lists the actions taken so far, oldest first. Undone actions are left out.".to_owned(),
+            Query::SaveHistory => "'save-history <path>' Writes the actions taken so far to a file, which '--script' can replay.\n\tRefused if a sink could have gone in more than one place, as the script couldn't tell which.".to_owned(),
+            Query::Percent => "'percent <on|off>' Shows the probabilities on the board as whole percentages.".to_owned(),
+            Query::Gradient => "'gradient <on|off>' Colors every untested coordinate from cold blue to hot red by its probability. Has no effect with '--plain'.".to_owned(),
+            Query::Resize => "'resize <width> <height>' Grows or shrinks the board on the right and bottom edges. Clears the history.".to_owned(),
+            Query::Simulate => format!("'simulate {args} <hit|miss>' {hint} Shows the board as it would be if the shot turned out that way, without firing it."),
+            Query::Mask => "'mask <threshold>' Marks the untested coordinates with a probability of at least the threshold (0 to 1) with '#', everything else with '.'.".to_owned(),
+            Query::Placements => "'placements <ship length>' Shows how many ways the ship can still be placed, and how many of them cover each coordinate.".to_owned(),
+            Query::Density => "'density <row|column>' Counts the untested coordinates in every row or column.".to_owned(),
+            Query::Goto => "'goto <step>' Shows the board as it was after that many actions, 0 being the start. Later actions can be gone back to, until a new one is taken.".to_owned(),
+            #[cfg(feature = "image")]
+            Query::Render => "'render <path>' Draws the current probabilities and shots to a PNG image.".to_owned(),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::types::IndexStyle;
+
+/*
+* House rules that change how ships may be placed, plus how coordinates are written.
+* The default is plain old Battleship, every toggle is opt-in.
+*/
+#[derive(Clone, Copy, Default)]
+pub struct Rules {
+    //how the heat of the different ship lengths is combined
+    pub combine_mode: CombineMode,
+    //ships may not share an edge or a corner with each other
+    pub ships_must_not_touch: bool,
+    //ships may also lie diagonally
+    pub allow_diagonal: bool,
+    //while hunting, only recommend cells on a grid spaced by the smallest remaining ship
+    pub parity_hunt: bool,
+    //one shot per remaining ship each turn, so a whole salvo gets recommended
+    pub salvo: bool,
+    //sinking a ship may also claim untested cells next to its hits, instead of requiring every cell to be hit
+    pub lenient_sink: bool,
+    //among equally good moves, prefer the ones closest to the center instead of the first in row-major order
+    pub center_tie_break: bool,
+    //also recommend the move expected to rule out the most placements
+    pub info_gain: bool,
+    //how coordinates are typed and shown
+    pub index_style: IndexStyle,
+}
+
+impl Rules {
+    //plain old Battleship, the same as the default but explicit about it
+    pub fn classic() -> Rules {
+        Rules {
+            combine_mode: CombineMode::Product,
+            ships_must_not_touch: false,
+            allow_diagonal: false,
+            parity_hunt: false,
+            salvo: false,
+            lenient_sink: false,
+            center_tie_break: false,
+            info_gain: false,
+            index_style: IndexStyle::ChessOneIndexed,
+        }
+    }
+
+    pub const NAMES: [&'static str; 7] = [
+        "no-touch",
+        "diagonal",
+        "parity",
+        "salvo",
+        "lenient-sink",
+        "center",
+        "info-gain",
+    ];
+
+    //looks a rule up by the name used on the command line
+    pub fn toggle_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "no-touch" => Some(&mut self.ships_must_not_touch),
+            "diagonal" => Some(&mut self.allow_diagonal),
+            "parity" => Some(&mut self.parity_hunt),
+            "salvo" => Some(&mut self.salvo),
+            "lenient-sink" => Some(&mut self.lenient_sink),
+            "center" => Some(&mut self.center_tie_break),
+            "info-gain" => Some(&mut self.info_gain),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub enum CombineMode {
+    //chance that at least one ship covers the cell, treating the ship lengths as independent
+    #[default]
+    Product,
+    //the chance of the single most likely ship length
+    Max,
+    //adds the chances up, which can exceed 1
+    Sum,
+}
+
+impl CombineMode {
+    pub const NAMES: [&'static str; 3] = ["product", "max", "sum"];
+
+    pub fn from_name(name: &str) -> Option<CombineMode> {
+        match name {
+            "product" => Some(CombineMode::Product),
+            "max" => Some(CombineMode::Max),
+            "sum" => Some(CombineMode::Sum),
+            _ => None,
+        }
+    }
+}
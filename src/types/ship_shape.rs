@@ -0,0 +1,45 @@
+/*
+* The footprint of a ship. Classic ships are a single cell wide, which is what every ship length stands for,
+* but some variants also field wider ships, like a 2x2 base.
+* A rotatable shape may also lie with its width and height swapped.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShipShape {
+    pub width: usize,
+    pub height: usize,
+    pub rotatable: bool,
+}
+
+impl ShipShape {
+    //the classic ship, which may lie either way
+    pub fn line(length: usize) -> ShipShape {
+        ShipShape {
+            width: length,
+            height: 1,
+            rotatable: true,
+        }
+    }
+
+    pub fn is_line(&self) -> bool {
+        self.width.min(self.height) == 1
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.width * self.height
+    }
+
+    //every distinct way the shape can lie, as (width, height)
+    pub fn orientations(&self) -> Vec<(usize, usize)> {
+        let mut orientations = vec![(self.width, self.height)];
+        if self.rotatable && self.width != self.height {
+            orientations.push((self.height, self.width));
+        }
+        orientations
+    }
+}
+
+impl From<usize> for ShipShape {
+    fn from(length: usize) -> ShipShape {
+        ShipShape::line(length)
+    }
+}